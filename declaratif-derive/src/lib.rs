@@ -0,0 +1,162 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{Data, DeriveInput, Fields, Ident, LitStr, parse_macro_input, spanned::Spanned};
+
+/// Generates binding functions and a default tree layout for a view model struct.
+///
+/// - `#[message]` on a field generates `fn <field>_message(&self) -> DisplayState<String>`, using
+///   the field's `Display` implementation, and adds a message line to the layout.
+/// - `#[progress(pos = "done", len = "total")]` on a field generates
+///   `fn <field>_progress(&self) -> DisplayState<ProgressBarState>`, reading
+///   `self.<field>.done` and `self.<field>.total`, and adds a progress bar to the layout.
+/// - `#[state]` on a field whose type implements `declaratif::ViewState` makes every binding
+///   follow that state. Without it, everything is always active.
+///
+/// The layout is available through `declaratif::ProgressView::progress_tree`, with a line per
+/// annotated field in declaration order.
+#[proc_macro_derive(ProgressView, attributes(progress, message, state))]
+pub fn derive_progress_view(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+enum Binding {
+    Message(Ident),
+    Progress {
+        field: Ident,
+        pos: Ident,
+        len: Ident,
+    },
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new(
+                    input.ident.span(),
+                    "ProgressView can only be derived for structs with named fields",
+                ));
+            }
+        },
+        _ => {
+            return Err(syn::Error::new(
+                input.ident.span(),
+                "ProgressView can only be derived for structs",
+            ));
+        }
+    };
+
+    let mut state_field = None;
+    let mut bindings = Vec::new();
+    for field in fields {
+        let name = field.ident.clone().unwrap();
+        for attr in field.attrs.iter() {
+            if attr.path().is_ident("state") {
+                attr.meta.require_path_only()?;
+                if state_field.is_some() {
+                    return Err(syn::Error::new(
+                        attr.span(),
+                        "only one field can be marked #[state]",
+                    ));
+                }
+                state_field = Some(name.clone());
+            } else if attr.path().is_ident("message") {
+                attr.meta.require_path_only()?;
+                bindings.push(Binding::Message(name.clone()));
+            } else if attr.path().is_ident("progress") {
+                let mut pos = None;
+                let mut len = None;
+                attr.parse_nested_meta(|meta| {
+                    let target = if meta.path.is_ident("pos") {
+                        &mut pos
+                    } else if meta.path.is_ident("len") {
+                        &mut len
+                    } else {
+                        return Err(meta.error("expected `pos` or `len`"));
+                    };
+                    let value: LitStr = meta.value()?.parse()?;
+                    *target = Some(value.parse::<Ident>()?);
+                    Ok(())
+                })?;
+
+                let missing = |key| {
+                    syn::Error::new(
+                        attr.span(),
+                        format!("#[progress] needs `{key} = \"<field>\"`"),
+                    )
+                };
+                bindings.push(Binding::Progress {
+                    field: name.clone(),
+                    pos: pos.ok_or_else(|| missing("pos"))?,
+                    len: len.ok_or_else(|| missing("len"))?,
+                });
+            }
+        }
+    }
+
+    let ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    let state = match &state_field {
+        Some(field) => quote! { ::declaratif::ViewState::display_state(&self.#field) },
+        None => quote! { ::declaratif::DisplayState::Active(()) },
+    };
+
+    let mut functions = Vec::new();
+    let mut leaves = Vec::new();
+    for binding in bindings.iter() {
+        match binding {
+            Binding::Message(field) => {
+                let function = format_ident!("{}_message", field);
+                functions.push(quote! {
+                    pub fn #function(&self) -> ::declaratif::DisplayState<::std::string::String> {
+                        let state: ::declaratif::DisplayState<()> = #state;
+                        state.map(|_| ::std::string::ToString::to_string(&self.#field))
+                    }
+                });
+                leaves.push(quote! {
+                    ::declaratif::helpers::widgets::message(Self::#function).into()
+                });
+            }
+            Binding::Progress { field, pos, len } => {
+                let function = format_ident!("{}_progress", field);
+                functions.push(quote! {
+                    #[allow(clippy::unnecessary_cast)]
+                    pub fn #function(
+                        &self,
+                    ) -> ::declaratif::DisplayState<::declaratif::ProgressBarState> {
+                        let state: ::declaratif::DisplayState<()> = #state;
+                        state.map(|_| {
+                            ::declaratif::ProgressBarState::new(
+                                None,
+                                None,
+                                self.#field.#pos as u64,
+                                self.#field.#len as u64,
+                            )
+                        })
+                    }
+                });
+                leaves.push(quote! {
+                    ::declaratif::helpers::widgets::progress_bar_default(Self::#function).into()
+                });
+            }
+        }
+    }
+
+    Ok(quote! {
+        impl #impl_generics #ident #type_generics #where_clause {
+            #(#functions)*
+        }
+
+        impl #impl_generics ::declaratif::ProgressView for #ident #type_generics #where_clause {
+            fn progress_tree() -> ::std::vec::Vec<::declaratif::ProgressBarTreeContainer<Self>> {
+                ::std::vec![#(#leaves),*]
+            }
+        }
+    })
+}
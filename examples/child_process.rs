@@ -0,0 +1,47 @@
+//! Runs a command and renders the progress it reports on stdout using the line protocol described
+//! in `declaratif::protocol`. Try it with:
+//!
+//! ```sh
+//! cargo run --example child_process --features json -- sh -c '
+//!   for i in 1 2 3 4 5; do
+//!     echo "{\"id\": \"job\", \"state\": \"active\", \"message\": \"Working\", \"pos\": $i, \"len\": 5}"
+//!     echo "{\"id\": \"step$i\", \"parent\": \"job\", \"state\": \"finished\"}"
+//!     sleep 1
+//!   done
+//!   echo "{\"id\": \"job\", \"state\": \"finished\"}"'
+//! ```
+use declaratif::{ProgressBarTree, protocol::spawn_task_reader, tasks::*};
+use indicatif::MultiProgress;
+use std::{
+    io::BufReader,
+    process::{Command, Stdio},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let program = args
+        .next()
+        .expect("Usage: child_process <command> [args...]");
+
+    let mut child = Command::new(program)
+        .args(args)
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to start command");
+
+    let model = Arc::new(TaskModel::new());
+    let reader = spawn_task_reader(BufReader::new(child.stdout.take().unwrap()), model.clone());
+
+    let tree = ProgressBarTree::new(MultiProgress::new(), vec![task_tree()]);
+    while !reader.is_finished() {
+        tree.tick(&model);
+        thread::sleep(Duration::from_millis(50));
+    }
+    tree.tick(&model);
+
+    reader.join().unwrap().expect("Failed to read from command");
+    child.wait().expect("Command wasn't running");
+}
@@ -1,6 +1,4 @@
-use declaratif::{
-    DisplayState, ProgressBarState, ProgressBarTree, helpers::tree::*, helpers::unbound::*,
-};
+use declaratif::{DisplayState, ProgressBarState, ProgressBarTree, helpers::widgets::*, tree};
 use indicatif::MultiProgress;
 use std::{
     sync::RwLock,
@@ -87,28 +85,40 @@ impl TestModel {
 fn main() {
     let multiprogress = MultiProgress::new();
 
-    let tree = ProgressBarTree::<TestModel>::new(multiprogress, vec![
-        progress_bar_default(TestModel::progress).into(),
-        message(TestModel::overall_message).into(),
-        message(TestModel::message_1).into(),
-        message(TestModel::message_2).into(),
-        spacer().into(),
-        // A group with a display condition
-        // The display condition is an inline closure rather than a function reference
-        group(vec![
-            message_static("== Message group that appears after 5 seconds!").into(),
-            message_static("  - This is a static message in a group").into(),
-            message_static("  - This is a static message as well").into(),
-            spacer().into(),
-        ]).with_display_condition(Box::new(|v| *v.0.read().unwrap() > 5.0)),
-        // You can convert a vec of ProgressBarBindable into a group with into()
-        vec![
-            message_static("== This is a static message in a group inside a group that is always visible").into(),
-            message_static("  - This is another static message in a group inside a group that is always visible"),
-            spacer().into(),
-        ].into(),
-        message(TestModel::overall_message_disappearing).into(),
-    ]);
+    let tree = ProgressBarTree::<TestModel>::new(
+        multiprogress,
+        tree! {
+            progress_bar_default(TestModel::progress),
+            message(TestModel::overall_message),
+            message(TestModel::message_2),
+            spacer(),
+            // A group with a display condition
+            // The display condition is an inline closure rather than a function reference
+            if |v: &TestModel| *v.0.read().unwrap() > 5.0 => {
+                message_static("Message group that appears after 5 seconds!"),
+                nested {
+                    message_static("This is a static message in a group"),
+                    message_static("This is a static message as well"),
+                    // Guides are redrawn as nested bars appear and disappear
+                    message(TestModel::message_1),
+                },
+                spacer(),
+            },
+            group {
+                message_static("This is a static message in a group that is always visible"),
+                nested {
+                    message_static("This is a nested message"),
+                    nested {
+                        message_static("This is nested another level down"),
+                    },
+                    message_static("This is the last nested message"),
+                },
+                spacer(),
+            },
+            message(TestModel::overall_message_disappearing),
+        },
+    )
+    .with_guides(true);
 
     let vm = TestModel(RwLock::new(0.0));
 
@@ -0,0 +1,201 @@
+//! Runs shell commands in parallel, showing a spinner and the latest line of output for each.
+//! Successful commands collapse to a single line, and the full output of any that failed is
+//! printed once everything has finished.
+//!
+//! ```sh
+//! declaratif "cargo build" "cargo test" "npm run lint"
+//! ```
+use declaratif::{DisplayState, ProgressBarTree, helpers::widgets::*};
+use indicatif::MultiProgress;
+use std::{
+    io::{BufRead, BufReader},
+    process::{Command, ExitCode, ExitStatus, Stdio},
+    sync::RwLock,
+    thread,
+    time::{Duration, Instant},
+};
+
+const USAGE: &str = "Usage: declaratif [options] [--] <command>...
+
+Runs each command with the system shell in parallel and shows its progress.
+
+Options:
+  -h, --help  Print this help";
+
+enum CommandStatus {
+    Running,
+    Exited(ExitStatus, Duration),
+    FailedToStart(String),
+    /// Started, but waiting for it to exit failed so its exit status is unknown
+    WaitFailed(String),
+}
+
+struct CommandRun {
+    command: String,
+    started: Instant,
+    status: CommandStatus,
+    output: Vec<String>,
+}
+
+// A ViewModel must be Send + Sync to be used in a multithreaded context
+struct RunModel {
+    runs: Vec<RwLock<CommandRun>>,
+}
+
+impl RunModel {
+    fn new(commands: Vec<String>) -> Self {
+        let started = Instant::now();
+        Self {
+            runs: commands
+                .into_iter()
+                .map(|command| {
+                    RwLock::new(CommandRun {
+                        command,
+                        started,
+                        status: CommandStatus::Running,
+                        output: Vec::new(),
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    fn all_finished(&self) -> bool {
+        self.runs
+            .iter()
+            .all(|run| !matches!(run.read().unwrap().status, CommandStatus::Running))
+    }
+
+    fn status(&self, index: usize) -> DisplayState<String> {
+        let run = self.runs[index].read().unwrap();
+        match &run.status {
+            CommandStatus::Running => match run.output.last() {
+                Some(line) => DisplayState::Active(format!("{}: {}", run.command, line.trim())),
+                None => DisplayState::Active(run.command.clone()),
+            },
+            CommandStatus::Exited(status, elapsed) if status.success() => {
+                DisplayState::Finished(format!("{} ({:.1}s)", run.command, elapsed.as_secs_f32()))
+            }
+            CommandStatus::Exited(status, _) => {
+                DisplayState::Failed(format!("{} ({})", run.command, status))
+            }
+            CommandStatus::FailedToStart(error) => {
+                DisplayState::Failed(format!("{} ({})", run.command, error))
+            }
+            CommandStatus::WaitFailed(error) => {
+                DisplayState::Failed(format!("{} (failed to wait: {})", run.command, error))
+            }
+        }
+    }
+}
+
+fn shell(command: &str) -> Command {
+    // Merge stderr into stdout so the output keeps its original interleaving
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C").arg(format!("{command} 2>&1"));
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c").arg(format!("exec 2>&1\n{command}"));
+        shell
+    }
+}
+
+fn run_command(run: &RwLock<CommandRun>) {
+    let command = run.read().unwrap().command.clone();
+    let child = shell(&command)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(error) => {
+            run.write().unwrap().status = CommandStatus::FailedToStart(error.to_string());
+            return;
+        }
+    };
+
+    // Read until the end even past output that isn't UTF-8, so the command isn't cut off by a
+    // closed pipe
+    let mut stdout = BufReader::new(child.stdout.take().unwrap());
+    let mut line = Vec::new();
+    while let Ok(1..) = stdout.read_until(b'\n', &mut line) {
+        let text = String::from_utf8_lossy(&line);
+        let text = text.trim_end_matches(['\n', '\r']).to_string();
+        run.write().unwrap().output.push(text);
+        line.clear();
+    }
+
+    let status = child.wait();
+    let mut run = run.write().unwrap();
+    run.status = match status {
+        Ok(status) => CommandStatus::Exited(status, run.started.elapsed()),
+        Err(error) => CommandStatus::WaitFailed(error.to_string()),
+    };
+}
+
+fn main() -> ExitCode {
+    // Options only come before the first command, so commands can have their own flags
+    let mut args = std::env::args().skip(1).peekable();
+    if let Some(arg) = args.next_if(|arg| arg.starts_with('-')) {
+        match arg.as_str() {
+            "--" => {}
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                return ExitCode::SUCCESS;
+            }
+            _ => {
+                eprintln!("Unknown option: {arg}\n\n{USAGE}");
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    let commands: Vec<String> = args.collect();
+    if commands.is_empty() {
+        eprintln!("{USAGE}");
+        return ExitCode::FAILURE;
+    }
+
+    let tree = ProgressBarTree::<RunModel>::new(
+        MultiProgress::new(),
+        (0..commands.len())
+            .map(|index| spinner(move |model: &RunModel| model.status(index)).into())
+            .collect(),
+    );
+
+    let model = RunModel::new(commands);
+
+    thread::scope(|s| {
+        for run in model.runs.iter() {
+            s.spawn(|| run_command(run));
+        }
+
+        while !model.all_finished() {
+            tree.tick(&model);
+            thread::sleep(Duration::from_millis(50));
+        }
+        tree.tick(&model);
+    });
+
+    let mut exit_code = ExitCode::SUCCESS;
+    for run in model.runs.iter() {
+        let run = run.read().unwrap();
+        let failure = match &run.status {
+            CommandStatus::Exited(status, _) if !status.success() => status.to_string(),
+            CommandStatus::FailedToStart(error) => error.clone(),
+            CommandStatus::WaitFailed(error) => format!("failed to wait: {error}"),
+            _ => continue,
+        };
+
+        exit_code = ExitCode::FAILURE;
+        eprintln!("\n==> {} failed ({})", run.command, failure);
+        for line in run.output.iter() {
+            eprintln!("{line}");
+        }
+    }
+
+    exit_code
+}
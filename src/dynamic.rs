@@ -0,0 +1,211 @@
+use crate::{
+    LeafUpdate, ProgressBarTreeContainer,
+    group::ChildComparator,
+    multiprogress_bindable::{BarPath, MultiProgressWrapper},
+    render::Resolved,
+    theme::Theme,
+};
+use std::sync::{Arc, Mutex};
+
+/// A group whose children are created and removed while the tree is running.
+pub struct DynamicGroup<V: Send + Sync>(Box<dyn DynamicChildren<V>>);
+
+impl<V: Send + Sync> DynamicGroup<V> {
+    pub(crate) fn new(children: impl DynamicChildren<V> + 'static) -> Self {
+        Self(Box::new(children))
+    }
+
+    pub(crate) fn reparent(&mut self, path: &BarPath, multiprogress: MultiProgressWrapper) {
+        self.0.reparent(path, multiprogress);
+    }
+
+    pub(crate) fn set_theme(&mut self, theme: &Arc<Theme>) {
+        self.0.set_theme(theme);
+    }
+
+    pub(crate) fn resolve(
+        &self,
+        model: &V,
+        can_display: bool,
+        path: &mut Vec<usize>,
+        observer: &mut dyn FnMut(&LeafUpdate<'_>),
+    ) -> Vec<(usize, Resolved)> {
+        self.0.resolve(model, can_display, path, observer)
+    }
+
+    pub(crate) fn visit(&self, visit: &mut dyn FnMut(&[&ProgressBarTreeContainer<V>])) {
+        self.0.visit(visit);
+    }
+}
+
+pub(crate) trait DynamicChildren<V: Send + Sync>: Send {
+    fn reparent(&mut self, path: &BarPath, multiprogress: MultiProgressWrapper);
+
+    /// Applies `theme` to the current children and any built later
+    fn set_theme(&mut self, theme: &Arc<Theme>);
+
+    /// Updates the children from the model and resolves them, in their declared order, along with
+    /// the last index of each child's path. Children that are removed are hidden straight
+    /// away.
+    fn resolve(
+        &self,
+        model: &V,
+        can_display: bool,
+        path: &mut Vec<usize>,
+        observer: &mut dyn FnMut(&LeafUpdate<'_>),
+    ) -> Vec<(usize, Resolved)>;
+
+    /// Passes the current children to `visit`, in the same order as [`Self::resolve`].
+    fn visit(&self, visit: &mut dyn FnMut(&[&ProgressBarTreeContainer<V>]));
+}
+
+type KeySource<V, K> = Box<dyn Fn(&V) -> Vec<K> + Send>;
+type ChildBuilder<V, K> = Box<dyn Fn(&K) -> ProgressBarTreeContainer<V> + Send>;
+
+/// Builds one child per key returned by the model. Children are kept in the order their keys first
+/// appeared, and each one gets a slot in the tree that it keeps for as long as its key is present.
+pub(crate) struct ForEach<V: Send + Sync, K> {
+    keys: KeySource<V, K>,
+    build: ChildBuilder<V, K>,
+    // Shared with the comparator sorting the children by key, if any
+    state: Arc<Mutex<ForEachState<V, K>>>,
+}
+
+struct ForEachState<V: Send + Sync, K> {
+    parent: Option<(BarPath, MultiProgressWrapper)>,
+    theme: Option<Arc<Theme>>,
+    next_slot: usize,
+    items: Vec<ForEachItem<V, K>>,
+}
+
+struct ForEachItem<V: Send + Sync, K> {
+    key: K,
+    slot: usize,
+    container: ProgressBarTreeContainer<V>,
+}
+
+impl<V: Send + Sync, K: PartialEq + Send> ForEach<V, K> {
+    pub(crate) fn new(
+        keys: impl Fn(&V) -> Vec<K> + 'static + Send,
+        build: impl Fn(&K) -> ProgressBarTreeContainer<V> + 'static + Send,
+    ) -> Self {
+        Self {
+            keys: Box::new(keys),
+            build: Box::new(build),
+            state: Arc::new(Mutex::new(ForEachState {
+                parent: None,
+                theme: None,
+                next_slot: 0,
+                items: Vec::new(),
+            })),
+        }
+    }
+
+    /// Compares the children, by their position in the order they're resolved, by `sort_key`
+    /// of their keys.
+    pub(crate) fn comparator<O: Ord>(
+        &self,
+        sort_key: impl Fn(&V, &K) -> O + Send + 'static,
+    ) -> ChildComparator<V>
+    where
+        V: 'static,
+        K: 'static,
+    {
+        let state = self.state.clone();
+        Box::new(move |model, a, b| {
+            let state = state.lock().unwrap();
+            let key = |index: usize| sort_key(model, &state.items[index].key);
+            key(a).cmp(&key(b))
+        })
+    }
+}
+
+impl<V: Send + Sync, K: PartialEq + Send> DynamicChildren<V> for ForEach<V, K> {
+    fn reparent(&mut self, path: &BarPath, multiprogress: MultiProgressWrapper) {
+        let mut state = self.state.lock().unwrap();
+        for item in state.items.iter_mut() {
+            let mut item_path = path.clone();
+            item_path.push(item.slot);
+            item.container
+                .reparent(&mut item_path, multiprogress.clone());
+        }
+        state.parent = Some((path.clone(), multiprogress));
+    }
+
+    fn set_theme(&mut self, theme: &Arc<Theme>) {
+        let mut state = self.state.lock().unwrap();
+        for item in state.items.iter_mut() {
+            item.container.set_theme(theme);
+        }
+        state.theme = Some(theme.clone());
+    }
+
+    fn resolve(
+        &self,
+        model: &V,
+        can_display: bool,
+        path: &mut Vec<usize>,
+        observer: &mut dyn FnMut(&LeafUpdate<'_>),
+    ) -> Vec<(usize, Resolved)> {
+        let mut state = self.state.lock().unwrap();
+
+        if can_display {
+            let keys = (self.keys)(model);
+
+            // Hide children whose key has gone before dropping them, so their bars are removed
+            let (kept, removed) = std::mem::take(&mut state.items)
+                .into_iter()
+                .partition::<Vec<_>, _>(|item| keys.contains(&item.key));
+            for item in removed {
+                path.push(item.slot);
+                item.container.tick_inner(model, false, path, observer);
+                if let Some((_, multiprogress)) = &state.parent {
+                    multiprogress.forget_positions(path);
+                }
+                path.pop();
+            }
+            state.items = kept;
+
+            for key in keys {
+                if state.items.iter().any(|item| item.key == key) {
+                    continue;
+                }
+
+                let slot = state.next_slot;
+                state.next_slot += 1;
+
+                let mut container = (self.build)(&key);
+                if let Some(theme) = &state.theme {
+                    container.set_theme(theme);
+                }
+                if let Some((parent_path, multiprogress)) = &state.parent {
+                    let mut item_path = parent_path.clone();
+                    item_path.push(slot);
+                    container.reparent(&mut item_path, multiprogress.clone());
+                }
+                state.items.push(ForEachItem {
+                    key,
+                    slot,
+                    container,
+                });
+            }
+        }
+
+        state
+            .items
+            .iter()
+            .map(|item| {
+                path.push(item.slot);
+                let resolved = item.container.resolve(model, can_display, path, observer);
+                path.pop();
+                (item.slot, resolved)
+            })
+            .collect()
+    }
+
+    fn visit(&self, visit: &mut dyn FnMut(&[&ProgressBarTreeContainer<V>])) {
+        let state = self.state.lock().unwrap();
+        let children: Vec<_> = state.items.iter().map(|item| &item.container).collect();
+        visit(&children);
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{DisplayState, ProgressBarState, theme::Theme};
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// State a widget adds to the bar it's drawn with, e.g. a chart's recent values or a countdown's
+/// deadline, along with the template keys that draw it. Bars keep a list of these rather than a
+/// field per widget.
+pub(crate) trait BarExtension<V>: Send {
+    /// Takes whatever the extension draws from the model on each tick.
+    fn record(&self, _model: &V) {}
+
+    /// Adds the extension's template keys to one of the bar's styles, drawn with `theme`.
+    fn style(&self, style: ProgressStyle, _theme: &Theme) -> ProgressStyle {
+        style
+    }
+
+    /// Called before `bar` is drawn with `state`. Returns whether the bar was reset, so it's given
+    /// its style again.
+    fn drawing(&self, _bar: &ProgressBar, _state: &DisplayState<ProgressBarState>) -> bool {
+        false
+    }
+
+    /// Called once the bar is drawn with `state`, or removed if there's no `bar`. `finish_style`
+    /// is the style the bar is given when it finishes.
+    fn drawn(
+        &self,
+        _bar: Option<&ProgressBar>,
+        _state: &DisplayState<ProgressBarState>,
+        _finish_style: Option<&ProgressStyle>,
+    ) {
+    }
+}
+
+/// Resets a finished bar when it's pending or active again, see
+/// [`crate::ProgressBarBindable::restartable`].
+pub(crate) struct Restart;
+
+impl<V> BarExtension<V> for Restart {
+    fn drawing(&self, bar: &ProgressBar, state: &DisplayState<ProgressBarState>) -> bool {
+        let restarted = bar.is_finished() && !state.is_finished();
+        if restarted {
+            bar.reset();
+        }
+        restarted
+    }
+}
@@ -0,0 +1,713 @@
+use crate::{
+    DisplayState, LeafUpdate, ProgressBarBindable, ProgressBarState,
+    multiprogress_bindable::{BarPath, MultiProgressWrapper},
+    progress_bar_bindable::ResolvedStyles,
+    render::{RenderContext, Resolved, ResolvedGroup, render_leaf},
+    summary::GroupSummary,
+    theme::{Theme, Widget},
+};
+use indicatif::ProgressState;
+use std::{
+    cmp::{Ordering, Reverse},
+    fmt,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
+
+/// When a group hides its children, leaving only its header and summary bars. See
+/// [`crate::ProgressBarTreeContainer::with_collapse`].
+#[derive(Default)]
+pub enum CollapsePolicy<V> {
+    #[default]
+    Never,
+    /// Once every child has finished successfully. Groups with failed children stay expanded so
+    /// the failures can be seen.
+    WhenFinished,
+    /// Like `WhenFinished`, but only once the children have been finished for the given time
+    WhenFinishedFor(Duration),
+    /// Whenever the function returns true
+    Manual(Box<dyn Fn(&V) -> bool + Send>),
+}
+
+/// The order a group draws its children in. See [`crate::ProgressBarTreeContainer::with_sort`].
+/// A child followed by nested groups without a header or summary moves along with them.
+#[derive(Default)]
+pub enum SortOrder<V> {
+    /// The order the children were declared in, or for a `for_each`, the order their keys first
+    /// appeared
+    #[default]
+    Declared,
+    /// Active children, then failed ones, then finished ones, most recently finished first
+    ActiveFirst,
+    /// The most recently changed children first
+    RecentlyUpdated,
+    /// Compares children by their position in declaration order, or for a `for_each`, the order
+    /// their keys first appeared. See [`SortOrder::by_key`] and
+    /// [`crate::helpers::tree::for_each_sorted`].
+    ByKey(ChildComparator<V>),
+}
+
+pub(crate) type ChildComparator<V> = Box<dyn Fn(&V, usize, usize) -> Ordering + Send>;
+
+impl<V> SortOrder<V> {
+    /// Sorts the children by a key extracted from the model, given each child's position in
+    /// declaration order. To sort the children of a `for_each` by their keys, use
+    /// [`crate::helpers::tree::for_each_sorted`].
+    pub fn by_key<K: Ord>(key: impl Fn(&V, usize) -> K + Send + 'static) -> Self {
+        SortOrder::ByKey(Box::new(move |model, a, b| {
+            key(model, a).cmp(&key(model, b))
+        }))
+    }
+
+    /// The items of `group` (see [`ResolvedGroup::items`]) in the order to draw them, if not
+    /// declaration order.
+    fn order(&self, model: &V, group: &ResolvedGroup) -> Option<Vec<usize>> {
+        let items = group.items();
+        let mut order: Vec<usize> = (0..items.len()).collect();
+        let children = |item: usize| &group.children[items[item].clone()];
+        let latest = |item: usize, time: fn(&Resolved) -> Option<Instant>| {
+            children(item).iter().filter_map(time).max()
+        };
+        match self {
+            SortOrder::Declared => return None,
+            SortOrder::ActiveFirst => order.sort_by_cached_key(|item| {
+                let state = GroupSummary::from_children(children(*item)).state();
+                Rank::new(&state, latest(*item, Resolved::finished_at))
+            }),
+            SortOrder::RecentlyUpdated => {
+                order.sort_by_cached_key(|item| Reverse(latest(*item, Resolved::updated_at)))
+            }
+            SortOrder::ByKey(compare) => {
+                order.sort_by(|a, b| compare(model, items[*a].start, items[*b].start))
+            }
+        }
+        Some(order)
+    }
+}
+
+/// Which children a group with more than its `max_visible` shows. See
+/// [`crate::ProgressBarTreeContainer::with_max_visible`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VisiblePriority {
+    /// Active children, then failed ones, then finished ones, most recently finished first
+    #[default]
+    ActiveFirst,
+    /// The first children drawn
+    First,
+    /// The last children drawn, e.g. the most recently added to a `for_each`
+    Last,
+}
+
+impl VisiblePriority {
+    /// Sorts `(index in tree order, rank)` pairs, most relevant first.
+    pub(crate) fn sort(self, entries: &mut [(usize, Rank)]) {
+        match self {
+            VisiblePriority::ActiveFirst => entries.sort_by_key(|(index, rank)| (*rank, *index)),
+            VisiblePriority::First => {}
+            VisiblePriority::Last => entries.reverse(),
+        }
+    }
+}
+
+/// How relevant a line is to [`VisiblePriority::ActiveFirst`], lowest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) struct Rank(u8, Reverse<Option<Instant>>);
+
+impl Rank {
+    pub(crate) fn new<T>(state: &DisplayState<T>, finished_at: Option<Instant>) -> Self {
+        let order = match state {
+            DisplayState::Active(_) => 0,
+            DisplayState::Failed(_) => 1,
+            DisplayState::Finished(_) => 2,
+            DisplayState::NotStarted | DisplayState::FinishedAndHidden => 3,
+        };
+        Rank(order, Reverse(finished_at))
+    }
+}
+
+/// Which parts of a group's bars are padded to the same width across the visible bars directly in
+/// the group, so they line up like a table. See [`crate::ProgressBarTreeContainer::with_columns`].
+///
+/// Only these parts are measured. Other keys, e.g. `{human_pos}` or those added with
+/// `ProgressStyle::with_key`, are drawn as they are; give them a fixed width in the template to
+/// line them up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Columns {
+    pub prefix: bool,
+    pub message: bool,
+    /// The `{pos}` and `{len}` keys, which are aligned to the right
+    pub position: bool,
+}
+
+impl Columns {
+    pub fn all() -> Self {
+        Columns {
+            prefix: true,
+            message: true,
+            position: true,
+        }
+    }
+
+    /// The widths of the columns across `states`.
+    pub(crate) fn measure<'a>(
+        self,
+        states: impl IntoIterator<Item = &'a ProgressBarState>,
+    ) -> ColumnWidths {
+        let text_width = |text: Option<&str>| text.map_or(0, console::measure_text_width);
+        let digits = |number: u64| number.to_string().len();
+        let mut widths = ColumnWidths::default();
+        for state in states {
+            if self.prefix {
+                widths.prefix = widths.prefix.max(text_width(state.prefix()));
+            }
+            if self.message {
+                widths.message = widths.message.max(text_width(state.message()));
+            }
+            if let (true, Some((position, length))) = (self.position, state.position_and_len()) {
+                widths.position = widths.position.max(digits(position));
+                widths.length = widths.length.max(digits(length));
+            }
+        }
+        widths
+    }
+}
+
+/// The widths a bar pads its prefix, message, `{pos}` and `{len}` to, so it lines up with its
+/// siblings. Zero leaves the field as it is.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) struct ColumnWidths {
+    pub(crate) prefix: usize,
+    pub(crate) message: usize,
+    pub(crate) position: usize,
+    pub(crate) length: usize,
+}
+
+impl ColumnWidths {
+    pub(crate) fn pads_position(&self) -> bool {
+        self.position > 0 || self.length > 0
+    }
+}
+
+/// A bar's widths, shared with its siblings and set by the tree on each tick, along with the bar's
+/// styles with `{pos}` and `{len}` replaced to pad them. Those styles are only used once the bar's
+/// group has widths for them, so other bars keep the built in keys.
+#[derive(Default)]
+pub(crate) struct ColumnAlignment {
+    widths: Arc<RwLock<ColumnWidths>>,
+    styles: ResolvedStyles,
+}
+
+impl ColumnAlignment {
+    pub(crate) fn widths(&self) -> ColumnWidths {
+        *self.widths.read().unwrap()
+    }
+
+    pub(crate) fn set_widths(&self, widths: ColumnWidths) {
+        *self.widths.write().unwrap() = widths;
+    }
+
+    /// The padded styles, made again from the bar's `styles` whenever they change.
+    pub(crate) fn styles(&self) -> &ResolvedStyles {
+        &self.styles
+    }
+
+    pub(crate) fn restyle(&mut self, styles: &ResolvedStyles) {
+        self.styles = styles.map(|style| {
+            let (pos_widths, len_widths) = (self.widths.clone(), self.widths.clone());
+            style
+                .clone()
+                .with_key(
+                    "pos",
+                    move |state: &ProgressState, w: &mut dyn fmt::Write| {
+                        let width = pos_widths.read().unwrap().position;
+                        let _ = write!(w, "{:>width$}", state.pos());
+                    },
+                )
+                .with_key(
+                    "len",
+                    move |state: &ProgressState, w: &mut dyn fmt::Write| {
+                        let width = len_widths.read().unwrap().length;
+                        let _ = write!(w, "{:>width$}", state.len().unwrap_or(state.pos()));
+                    },
+                )
+        });
+    }
+}
+
+/// How a group lays out its children.
+pub struct GroupOptions<V> {
+    pub(crate) nested: bool,
+    pub(crate) header: Option<Box<ProgressBarBindable<GroupSummary>>>,
+    pub(crate) summary: Option<Box<ProgressBarBindable<GroupSummary>>>,
+    pub(crate) collapse: CollapsePolicy<V>,
+    pub(crate) max_visible: Option<usize>,
+    pub(crate) priority: VisiblePriority,
+    // Created along with `max_visible`
+    pub(crate) overflow: Option<Box<ProgressBarBindable<GroupSummary>>>,
+    pub(crate) sort: SortOrder<V>,
+    pub(crate) columns: Columns,
+    timing: Mutex<GroupTiming>,
+    // Where the group is drawn, for moving its children when they're sorted
+    parent: Option<(BarPath, MultiProgressWrapper)>,
+}
+
+impl<V> Default for GroupOptions<V> {
+    fn default() -> Self {
+        Self {
+            nested: false,
+            header: None,
+            summary: None,
+            collapse: CollapsePolicy::Never,
+            max_visible: None,
+            priority: VisiblePriority::default(),
+            overflow: None,
+            sort: SortOrder::default(),
+            columns: Columns::default(),
+            timing: Mutex::new(GroupTiming::default()),
+            parent: None,
+        }
+    }
+}
+
+// When the group's children started and finished, for the summary's elapsed time
+#[derive(Default)]
+struct GroupTiming {
+    started: Option<Instant>,
+    finished: Option<Instant>,
+}
+
+// The overflow and summary bars sort after every child
+const OVERFLOW_SLOT: usize = usize::MAX - 1;
+const SUMMARY_SLOT: usize = usize::MAX;
+
+impl<V> GroupOptions<V> {
+    pub(crate) fn set_max_visible(&mut self, max_visible: usize) {
+        self.max_visible = Some(max_visible);
+        self.overflow.get_or_insert_with(|| {
+            Box::new(ProgressBarBindable::themed(Widget::Overflow).bind_message(
+                |summary: &GroupSummary| DisplayState::Active(summary.overflow_message()),
+            ))
+        });
+    }
+
+    pub(crate) fn resolve(
+        &self,
+        model: &V,
+        children: Vec<(usize, Resolved)>,
+        can_display: bool,
+        path: &mut Vec<usize>,
+        observer: &mut dyn FnMut(&LeafUpdate<'_>),
+    ) -> ResolvedGroup {
+        let (slots, children) = children.into_iter().unzip();
+        let mut group = ResolvedGroup {
+            children,
+            slots,
+            order: None,
+            header: None,
+            summary: None,
+            overflow: None,
+            collapsed: false,
+            continues_previous: self.nested && self.header.is_none() && self.summary.is_none(),
+            shown: None,
+        };
+
+        let needs_summary = self.header.is_some()
+            || self.summary.is_some()
+            || matches!(
+                self.collapse,
+                CollapsePolicy::WhenFinished | CollapsePolicy::WhenFinishedFor(_)
+            );
+        let mut summary = GroupSummary::default();
+        let mut finished_for = None;
+        if needs_summary {
+            summary = GroupSummary::from_children(&group.children);
+            finished_for = self.update_timing(&mut summary);
+        }
+
+        group.collapsed = match &self.collapse {
+            CollapsePolicy::Never => false,
+            CollapsePolicy::WhenFinished => finished_for.is_some() && summary.failed == 0,
+            CollapsePolicy::WhenFinishedFor(delay) => {
+                finished_for.is_some_and(|finished_for| finished_for >= *delay)
+                    && summary.failed == 0
+            }
+            CollapsePolicy::Manual(collapse) => collapse(model),
+        };
+
+        // The header takes the group's path, which sorts before all of its children
+        group.header = self
+            .header
+            .as_ref()
+            .map(|header| Self::resolve_bar(header, &summary, can_display, path, observer));
+        group.summary = self.summary.as_ref().map(|bar| {
+            path.push(SUMMARY_SLOT);
+            let state = Self::resolve_bar(
+                bar,
+                &summary,
+                can_display && group.collapsed,
+                path,
+                observer,
+            );
+            path.pop();
+            state
+        });
+
+        group.order = self.sort.order(model, &group);
+
+        if let (Some(max_visible), Some(overflow)) = (self.max_visible, &self.overflow) {
+            if !group.collapsed {
+                group.shown = self.select_shown(&group, max_visible);
+            }
+
+            let items = group.items();
+            let left_out = items
+                .into_iter()
+                .enumerate()
+                .filter(|(index, _)| !group.is_shown(*index))
+                .flat_map(|(_, item)| &group.children[item]);
+            let left_out = GroupSummary::from_children(left_out);
+
+            path.push(OVERFLOW_SLOT);
+            group.overflow = Some(Self::resolve_bar(
+                overflow,
+                &left_out,
+                can_display && group.shown.is_some(),
+                path,
+                observer,
+            ));
+            path.pop();
+        }
+
+        group
+    }
+
+    fn resolve_bar(
+        bar: &ProgressBarBindable<GroupSummary>,
+        summary: &GroupSummary,
+        can_display: bool,
+        path: &[usize],
+        observer: &mut dyn FnMut(&LeafUpdate<'_>),
+    ) -> DisplayState<ProgressBarState> {
+        let state = bar.resolve(summary, can_display);
+        observer(&LeafUpdate {
+            id: bar.id(),
+            path,
+            state: &state,
+        });
+        state
+    }
+
+    /// Picks which items to show when more than `max_visible` of them are visible.
+    fn select_shown(&self, group: &ResolvedGroup, max_visible: usize) -> Option<Vec<bool>> {
+        let mut visible: Vec<(usize, Rank)> = group
+            .ordered_items()
+            .iter()
+            .filter(|(_, item)| {
+                group.children[(*item).clone()]
+                    .iter()
+                    .any(Resolved::is_visible)
+            })
+            .map(|(index, item)| {
+                let children = &group.children[item.clone()];
+                let state = GroupSummary::from_children(children).state();
+                let finished_at = children.iter().filter_map(Resolved::finished_at).max();
+                (*index, Rank::new(&state, finished_at))
+            })
+            .collect();
+        if visible.len() <= max_visible {
+            return None;
+        }
+
+        self.priority.sort(&mut visible);
+        let mut shown = vec![false; group.items().len()];
+        for (index, _) in visible.into_iter().take(max_visible) {
+            shown[index] = true;
+        }
+        Some(shown)
+    }
+
+    /// Fills in the summary's elapsed time, returning how long the children have all been done.
+    fn update_timing(&self, summary: &mut GroupSummary) -> Option<Duration> {
+        let mut timing = self.timing.lock().unwrap();
+        let now = Instant::now();
+        if summary.not_started < summary.total {
+            timing.started.get_or_insert(now);
+        }
+        if summary.total > 0 && summary.done() == summary.total {
+            timing.finished.get_or_insert(now);
+        } else {
+            timing.finished = None;
+        }
+
+        summary.elapsed = timing
+            .started
+            .map(|started| timing.finished.unwrap_or(now).duration_since(started));
+        timing.finished.map(|finished| now.duration_since(finished))
+    }
+
+    /// Moves the children's bars into the order they're drawn in, if the group is sorted.
+    pub(crate) fn place_children(&self, group: &ResolvedGroup) {
+        if let (Some(order), Some((path, multiprogress))) = (&group.order, &self.parent) {
+            let items = group.items();
+            let slots: Vec<usize> = order
+                .iter()
+                .flat_map(|item| group.slots[items[*item].clone()].iter().copied())
+                .collect();
+            multiprogress.place_children(path, &slots);
+        }
+    }
+
+    pub(crate) fn render_header(
+        &self,
+        state: &Option<DisplayState<ProgressBarState>>,
+        context: &mut RenderContext,
+    ) {
+        if let (Some(header), Some(state)) = (&self.header, state) {
+            render_leaf(header, state, context);
+        }
+    }
+
+    /// Draws the summary in place of the children, one level deeper if the group is nested.
+    pub(crate) fn render_summary(
+        &self,
+        state: &Option<DisplayState<ProgressBarState>>,
+        context: &mut RenderContext,
+    ) {
+        self.render_last_item(&self.summary, state, context);
+    }
+
+    /// Draws the overflow line after the children, one level deeper if the group is nested.
+    pub(crate) fn render_overflow(
+        &self,
+        state: &Option<DisplayState<ProgressBarState>>,
+        context: &mut RenderContext,
+    ) {
+        self.render_last_item(&self.overflow, state, context);
+    }
+
+    fn render_last_item(
+        &self,
+        bar: &Option<Box<ProgressBarBindable<GroupSummary>>>,
+        state: &Option<DisplayState<ProgressBarState>>,
+        context: &mut RenderContext,
+    ) {
+        if let (Some(bar), Some(state)) = (bar, state) {
+            if self.nested {
+                context.nested(true, |context| render_leaf(bar, state, context));
+            } else {
+                render_leaf(bar, state, context);
+            }
+        }
+    }
+
+    pub(crate) fn hide(&self) {
+        for bar in self.bars() {
+            bar.render(&DisplayState::FinishedAndHidden);
+        }
+    }
+
+    pub(crate) fn reparent(&mut self, path: &BarPath, multiprogress: &MultiProgressWrapper) {
+        self.parent = Some((path.clone(), multiprogress.clone()));
+        if let Some(header) = &mut self.header {
+            header.reparent(multiprogress.clone(), path.clone());
+        }
+        for (bar, slot) in [
+            (&mut self.overflow, OVERFLOW_SLOT),
+            (&mut self.summary, SUMMARY_SLOT),
+        ] {
+            if let Some(bar) = bar {
+                let mut bar_path = path.clone();
+                bar_path.push(slot);
+                bar.reparent(multiprogress.clone(), bar_path);
+            }
+        }
+    }
+
+    pub(crate) fn set_theme(&mut self, theme: &Arc<Theme>) {
+        for bar in [&mut self.header, &mut self.summary, &mut self.overflow]
+            .into_iter()
+            .flatten()
+        {
+            bar.set_theme(theme);
+        }
+    }
+
+    fn bars(&self) -> impl Iterator<Item = &ProgressBarBindable<GroupSummary>> {
+        [&self.header, &self.summary, &self.overflow]
+            .into_iter()
+            .flatten()
+            .map(|bar| bar.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ProgressBarTree, TerminalCapabilities,
+        helpers::{
+            tree::{for_each, for_each_sorted, group},
+            widgets::*,
+        },
+    };
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    type Model = Vec<DisplayState<String>>;
+
+    fn jobs(term: &InMemoryTerm, priority: VisiblePriority) -> ProgressBarTree<Model> {
+        ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                message_static("jobs").into(),
+                for_each(
+                    |model: &Model| (0..model.len()).collect(),
+                    |index: &usize| {
+                        let index = *index;
+                        message(move |model: &Model| model[index].clone()).into()
+                    },
+                )
+                .nested()
+                .with_max_visible(2)
+                .with_visible_priority(priority),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only())
+    }
+
+    #[test]
+    fn test_max_visible() {
+        let finished = |name: &str| DisplayState::Finished(name.to_string());
+        let active = |name: &str| DisplayState::Active(name.to_string());
+        let model = vec![
+            finished("a"),
+            finished("b"),
+            active("c"),
+            DisplayState::NotStarted,
+            active("e"),
+        ];
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree = jobs(&term, VisiblePriority::ActiveFirst);
+        tree.tick(&model);
+        assert_eq!(
+            term.contents(),
+            "jobs\n  c\n  e\n  ... and 3 more (1 queued, 2 done)"
+        );
+
+        // Everything fits once only two are visible
+        tree.tick(&vec![finished("a"), active("b")]);
+        assert_eq!(term.contents(), "jobs\n  a\n  b");
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree = jobs(&term, VisiblePriority::First).with_guides(true);
+        tree.tick(&model);
+        assert_eq!(
+            term.contents(),
+            "jobs\n|- a\n|- b\n`- ... and 3 more (2 active, 1 queued)"
+        );
+    }
+
+    #[test]
+    fn test_sort() {
+        let model = |states: [DisplayState<String>; 3]| Vec::from(states);
+        let active = |name: &str| DisplayState::Active(name.to_string());
+        let finished = |name: &str| DisplayState::Finished(name.to_string());
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                for_each(
+                    |model: &Model| (0..model.len()).collect(),
+                    |index: &usize| {
+                        let index = *index;
+                        message(move |model: &Model| model[index].clone()).into()
+                    },
+                )
+                .with_sort(SortOrder::ActiveFirst),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        tree.tick(&model([finished("a"), active("b"), active("c")]));
+        assert_eq!(term.contents(), "b\nc\na");
+        tree.tick(&model([finished("a"), finished("b"), active("c")]));
+        assert_eq!(term.contents(), "c\nb\na");
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                group(vec![
+                    message(|model: &Model| model[0].clone()).into(),
+                    message(|model: &Model| model[1].clone()).into(),
+                    message(|model: &Model| model[2].clone()).into(),
+                ])
+                .with_sort(SortOrder::by_key(|model: &Model, index| {
+                    format!("{:?}", model[index])
+                })),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+        tree.tick(&model([active("c"), active("a"), active("b")]));
+        assert_eq!(term.contents(), "a\nb\nc");
+    }
+
+    #[test]
+    fn test_sort_for_each_by_key() {
+        // Downloads by name, with their sizes
+        type Downloads = Vec<(&'static str, u64)>;
+        let size = |model: &Downloads, name: &&str| {
+            model
+                .iter()
+                .find(|(other, _)| other == name)
+                .map(|(_, size)| *size)
+        };
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![for_each_sorted(
+                |model: &Downloads| model.iter().map(|(name, _)| *name).collect(),
+                move |model, name| Reverse(size(model, name)),
+                |name: &&str| message_static(*name).into(),
+            )],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        tree.tick(&vec![("a", 3), ("b", 1), ("c", 2)]);
+        assert_eq!(term.contents(), "a\nc\nb");
+
+        // Sizes are read from the model on every tick, and new keys are sorted in too
+        tree.tick(&vec![("b", 5), ("c", 2), ("d", 4)]);
+        assert_eq!(term.contents(), "b\nd\nc");
+    }
+
+    #[test]
+    fn test_columns() {
+        let term = InMemoryTerm::new(10, 40);
+        let task = |index: usize| {
+            from_template_str("{prefix}: [{pos}/{len}] {msg} |")
+                .bind_progress(move |model: &Vec<(&str, &str, u64, u64)>| {
+                    let (prefix, message, position, length) = model[index];
+                    DisplayState::Active(ProgressBarState::new(
+                        Some(message.to_string()),
+                        Some(prefix.to_string()),
+                        position,
+                        length,
+                    ))
+                })
+                .into()
+        };
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![group(vec![task(0), task(1)]).with_columns(Columns::all())],
+        );
+
+        tree.tick(&vec![("a", "foo", 1, 3), ("bc", "barbaz", 10, 30)]);
+        assert_eq!(
+            term.contents(),
+            "a : [ 1/ 3] foo    |\nbc: [10/30] barbaz |"
+        );
+    }
+}
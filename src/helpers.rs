@@ -1,106 +1,367 @@
-use crate::{DisplayState, ProgressBarBindable, ProgressBarState, ProgressBarTreeContainer};
+use crate::{
+    Columns, DisplayState, GroupOptions, GroupSummary, History, LogTail, ProgressBarBindable,
+    ProgressBarState, ProgressBarTreeContainer, SortOrder, Step, TailOptions, TimeFormat, Timer,
+    Widget,
+    dynamic::{DynamicGroup, ForEach},
+    tail::{LineSource, Tail},
+    timer::TIMER_TICK,
+};
 use indicatif::ProgressStyle;
+use std::{sync::Arc, time::Instant};
 
-// TODO: Fix up the duplication here
 pub mod tree {
     use super::*;
     pub fn group<V: Send + Sync>(
         children: Vec<ProgressBarTreeContainer<V>>,
     ) -> ProgressBarTreeContainer<V> {
-        ProgressBarTreeContainer::Node(children, None)
+        ProgressBarTreeContainer::Node(children, None, GroupOptions::default())
+    }
+
+    /// A group drawn one level deeper than its surroundings, see
+    /// [`crate::ProgressBarTree::with_guides`].
+    pub fn nested<V: Send + Sync>(
+        children: Vec<ProgressBarTreeContainer<V>>,
+    ) -> ProgressBarTreeContainer<V> {
+        group(children).nested()
     }
 
     pub fn single<V: Send + Sync>(bar: ProgressBarBindable<V>) -> ProgressBarTreeContainer<V> {
         bar.into()
     }
+
+    /// A group with one child per key returned by `keys`, built with `build` the first time the
+    /// key appears and removed when it's no longer returned.
+    pub fn for_each<V: Send + Sync + 'static, K: PartialEq + Send + 'static>(
+        keys: impl Fn(&V) -> Vec<K> + 'static + Send,
+        build: impl Fn(&K) -> ProgressBarTreeContainer<V> + 'static + Send,
+    ) -> ProgressBarTreeContainer<V> {
+        ProgressBarTreeContainer::Dynamic(
+            DynamicGroup::new(ForEach::new(keys, build)),
+            None,
+            GroupOptions::default(),
+        )
+    }
+
+    /// Like [`for_each`], but draws the children sorted by `sort_key`, which is taken from the
+    /// model and each child's key on every tick, e.g. to order downloads by size.
+    pub fn for_each_sorted<V: Send + Sync + 'static, K: PartialEq + Send + 'static, O: Ord>(
+        keys: impl Fn(&V) -> Vec<K> + 'static + Send,
+        sort_key: impl Fn(&V, &K) -> O + 'static + Send,
+        build: impl Fn(&K) -> ProgressBarTreeContainer<V> + 'static + Send,
+    ) -> ProgressBarTreeContainer<V> {
+        let children = ForEach::new(keys, build);
+        let order = SortOrder::ByKey(children.comparator(sort_key));
+        ProgressBarTreeContainer::Dynamic(
+            DynamicGroup::new(children),
+            None,
+            GroupOptions::default(),
+        )
+        .with_sort(order)
+    }
+
+    /// Shows only the children whose key equals `selector(&model)`.
+    pub fn switch<V: Send + Sync, K: PartialEq + Send + 'static>(
+        selector: impl Fn(&V) -> K + 'static + Send + Sync,
+        cases: Vec<(K, ProgressBarTreeContainer<V>)>,
+    ) -> ProgressBarTreeContainer<V> {
+        let selector = Arc::new(selector);
+        group(
+            cases
+                .into_iter()
+                .map(|(key, child)| {
+                    let selector = selector.clone();
+                    // Wrapped so the case keeps any display condition of its own
+                    group(vec![child]).with_display_condition(Box::new(move |v| selector(v) == key))
+                })
+                .collect(),
+        )
+    }
+
+    /// A panel of `rows` lines showing the most recent lines of `tail`, oldest first. Rows are
+    /// hidden until there are enough lines to fill them.
+    pub fn log_tail<V: Send + Sync + 'static>(
+        tail: &LogTail,
+        rows: usize,
+    ) -> ProgressBarTreeContainer<V> {
+        let options = TailOptions {
+            rows,
+            fill: false,
+            dim_older: false,
+        };
+        ProgressBarTreeContainer::Dynamic(
+            DynamicGroup::new(Tail::new(LineSource::Log(tail.clone()), options)),
+            None,
+            GroupOptions::default(),
+        )
+    }
+
+    /// A window onto the end of some output held in the model, like the live output cargo shows
+    /// while building. Draws the last `options.rows` of the lines returned by `lines`, oldest
+    /// first, each cut off at the edge of the terminal.
+    pub fn tail<V: Send + Sync + 'static>(
+        lines: impl for<'a> Fn(&'a V) -> &'a [String] + Send + 'static,
+        options: TailOptions,
+    ) -> ProgressBarTreeContainer<V> {
+        ProgressBarTreeContainer::Dynamic(
+            DynamicGroup::new(Tail::new(LineSource::Model(Box::new(lines)), options)),
+            None,
+            GroupOptions::default(),
+        )
+    }
+
+    /// A fixed list of steps, e.g. resolve, download, verify and install, each drawn with a glyph
+    /// for its [`crate::StepStatus`] and how long it took. A running step shows its details
+    /// underneath it.
+    pub fn checklist<V: Send + Sync + 'static>(steps: Vec<Step<V>>) -> ProgressBarTreeContainer<V> {
+        group(steps.into_iter().map(Step::build).collect())
+    }
+
+    /// A block of `"label: value"` lines, e.g. `"Files:      1234"`, with the values lined up
+    /// after the longest visible label. Lines whose value is empty are hidden.
+    ///
+    /// ```
+    /// # use declaratif::{DisplayState, ProgressBarTreeContainer, helpers::tree::panel};
+    /// struct Stats {
+    ///     files: usize,
+    ///     errors: usize,
+    /// }
+    ///
+    /// let stats: ProgressBarTreeContainer<Stats> = panel()
+    ///     .field("Files", |stats: &Stats| DisplayState::Active(stats.files.to_string()))
+    ///     .field("Errors", |stats: &Stats| DisplayState::Active(stats.errors.to_string()))
+    ///     .into();
+    /// ```
+    pub fn panel<V>() -> Panel<V> {
+        Panel { fields: Vec::new() }
+    }
+}
+
+/// The lines of a [`tree::panel`], added with [`Panel::field`].
+pub struct Panel<V> {
+    fields: Vec<ProgressBarBindable<V>>,
 }
 
-pub mod unbound {
+impl<V> Panel<V> {
+    /// Adds a line with `label` and the value returned by `updater`, see [`widgets::field`].
+    pub fn field(
+        mut self,
+        label: impl Into<String>,
+        updater: impl Fn(&V) -> DisplayState<String> + 'static + Send,
+    ) -> Self {
+        self.fields.push(widgets::field(label, updater));
+        self
+    }
+}
+
+impl<V: Send + Sync + 'static> From<Panel<V>> for ProgressBarTreeContainer<V> {
+    fn from(panel: Panel<V>) -> Self {
+        tree::group(panel.fields.into_iter().map(Into::into).collect()).with_columns(Columns {
+            prefix: true,
+            ..Columns::default()
+        })
+    }
+}
+
+/// Every built in widget. The bars draw nothing until they're placed in a
+/// [`crate::ProgressBarTree`]; to tick one on its own, give it
+/// `.with_target(BindingTarget::Standalone)`.
+pub mod widgets {
     use super::*;
     /// Creates a new ProgressBarBindable with the passed indicatif template.
     /// Will panic if the template is invalid.
-    pub fn from_template_str<V: Send + Sync>(template: &str) -> ProgressBarBindable<V> {
+    pub fn from_template_str<V>(template: &str) -> ProgressBarBindable<V> {
         let style = ProgressStyle::with_template(template)
             .expect("Invalid template string for ProgressBarBindable");
         ProgressBarBindable::new(style)
     }
 
-    pub fn styled<V: Send + Sync>(style: ProgressStyle) -> ProgressBarBindable<V> {
+    pub fn styled<V>(style: ProgressStyle) -> ProgressBarBindable<V> {
         ProgressBarBindable::new(style)
     }
 
-    pub fn spinner<V: Send + Sync>(
+    pub fn spinner<V>(
         updater: impl Fn(&V) -> DisplayState<String> + 'static + Send,
     ) -> ProgressBarBindable<V> {
-        ProgressBarBindable::new(ProgressStyle::default_spinner()).bind_message(updater)
+        ProgressBarBindable::themed(Widget::Spinner).bind_message(updater)
     }
 
-    pub fn spinner_post<V: Send + Sync>(
+    pub fn spinner_post<V>(
         updater: impl Fn(&V) -> DisplayState<String> + 'static + Send,
     ) -> ProgressBarBindable<V> {
-        ProgressBarBindable::new(ProgressStyle::with_template("{msg} {spinner}").unwrap())
-            .bind_message(updater)
+        ProgressBarBindable::themed(Widget::SpinnerPost).bind_message(updater)
     }
 
-    pub fn spacer<V: Send + Sync>() -> ProgressBarBindable<V> {
-        message_static(" ".to_string())
+    /// A blank line. Uses a space since indicatif doesn't draw empty lines.
+    pub fn spacer<V>() -> ProgressBarBindable<V> {
+        message_static(" ")
     }
 
-    pub fn message_static<V: Send + Sync>(message: impl Into<String>) -> ProgressBarBindable<V> {
-        let style = ProgressStyle::with_template("{msg}").unwrap();
-        ProgressBarBindable::new(style).with_static_message(message.into())
+    pub fn message_static<V>(message: impl Into<String>) -> ProgressBarBindable<V> {
+        ProgressBarBindable::themed(Widget::Message).with_static_message(message)
     }
 
-    pub fn message<V: Send + Sync>(
+    pub fn message<V>(
         updater: impl Fn(&V) -> DisplayState<String> + 'static + Send,
     ) -> ProgressBarBindable<V> {
-        let style = ProgressStyle::with_template("{msg}").unwrap();
-
-        ProgressBarBindable::new_standalone(style).bind_message(updater)
+        ProgressBarBindable::themed(Widget::Message).bind_message(updater)
     }
 
-    pub fn progress_bar_default<V: Send + Sync>(
-        updater: impl Fn(&V) -> DisplayState<ProgressBarState> + 'static + Send,
+    /// `label` followed by the value returned by `updater`, hidden while the value is empty. See
+    /// [`super::tree::panel`] for lining up several of them.
+    pub fn field<V>(
+        label: impl Into<String>,
+        updater: impl Fn(&V) -> DisplayState<String> + 'static + Send,
     ) -> ProgressBarBindable<V> {
-        ProgressBarBindable::new(ProgressStyle::default_bar()).bind_progress(updater)
+        ProgressBarBindable::themed(Widget::Field)
+            .with_static_prefix(format!("{}:", label.into()))
+            .bind_message(move |model| match updater(model) {
+                DisplayState::Active(value)
+                | DisplayState::Finished(value)
+                | DisplayState::Failed(value)
+                    if value.is_empty() =>
+                {
+                    DisplayState::NotStarted
+                }
+                state => state,
+            })
     }
-}
 
-pub mod standalone {
-    use super::*;
-    /// Creates a new ProgressBarBindable with the passed indicatif template.
-    /// Will panic if the template is invalid.
-    pub fn from_template_str<V>(template: &str) -> ProgressBarBindable<V> {
-        let style = ProgressStyle::with_template(template)
-            .expect("Invalid template string for ProgressBarBindable");
-        ProgressBarBindable::new_standalone(style)
+    /// The message followed by a chart of the recent values of `sample`, e.g. the throughput of
+    /// a download as a sparkline.
+    pub fn chart<V>(
+        updater: impl Fn(&V) -> DisplayState<String> + 'static + Send,
+        sample: impl Fn(&V) -> f64 + 'static + Send,
+        history: History,
+    ) -> ProgressBarBindable<V> {
+        ProgressBarBindable::themed(Widget::Chart)
+            .bind_message(updater)
+            .with_history("history", sample, history)
     }
 
-    pub fn styled<V>(style: ProgressStyle) -> ProgressBarBindable<V> {
-        ProgressBarBindable::new_standalone(style)
+    /// `label` followed by the time left until `deadline`, e.g. `"retrying in 7s"`. Counts down
+    /// between ticks and finishes once the deadline has passed. Hidden while there's no deadline.
+    pub fn countdown<V>(
+        label: impl Into<String>,
+        deadline: impl Fn(&V) -> Option<Instant> + 'static + Send + Sync,
+        format: TimeFormat,
+    ) -> ProgressBarBindable<V> {
+        let (label, deadline) = (label.into(), Arc::new(deadline));
+        let (state, timer) = (deadline.clone(), deadline.clone());
+        ProgressBarBindable::themed(Widget::Timer)
+            .bind_message(move |model| match state(model) {
+                Some(deadline) if deadline > Instant::now() => DisplayState::Active(label.clone()),
+                Some(_) => DisplayState::Finished(label.clone()),
+                None => DisplayState::NotStarted,
+            })
+            .with_timer("timer", move |model| timer(model), Timer::Remaining(format))
+            .with_deadline(move |model| deadline(model))
+            .with_steady_tick(TIMER_TICK)
+            .restartable()
     }
 
-    pub fn spacer<V>() -> ProgressBarBindable<V> {
-        message_static("".to_string())
+    /// `label` followed by the time since `start`, e.g. `"running for 1m12s"`, counting up
+    /// between ticks. Hidden while there's no start.
+    pub fn stopwatch<V>(
+        label: impl Into<String>,
+        start: impl Fn(&V) -> Option<Instant> + 'static + Send + Sync,
+        format: TimeFormat,
+    ) -> ProgressBarBindable<V> {
+        let (label, start) = (label.into(), Arc::new(start));
+        let state = start.clone();
+        ProgressBarBindable::themed(Widget::Timer)
+            .bind_message(move |model| match state(model) {
+                Some(_) => DisplayState::Active(label.clone()),
+                None => DisplayState::NotStarted,
+            })
+            .with_timer("timer", move |model| start(model), Timer::Elapsed(format))
+            .with_steady_tick(TIMER_TICK)
     }
 
-    pub fn message_static<V>(message: impl Into<String>) -> ProgressBarBindable<V> {
-        let style = ProgressStyle::with_template("{msg}").unwrap();
-
-        ProgressBarBindable::new_standalone(style).with_static_message(message.into())
+    /// A group header showing `label` and how many of the group's children are done, see
+    /// [`crate::ProgressBarTreeContainer::with_header`].
+    pub fn header_counts(label: impl Into<String>) -> ProgressBarBindable<GroupSummary> {
+        let label = label.into();
+        ProgressBarBindable::themed(Widget::Spinner)
+            .bind_message(move |summary: &GroupSummary| summary.counts_message(&label))
     }
 
-    pub fn message<V>(
-        updater: impl Fn(&V) -> DisplayState<String> + 'static + Send,
-    ) -> ProgressBarBindable<V> {
-        let style = ProgressStyle::with_template("{msg}").unwrap();
+    /// A group header showing `label` and a bar with the combined progress of the group's
+    /// children.
+    pub fn header_progress(label: impl Into<String>) -> ProgressBarBindable<GroupSummary> {
+        let label = label.into();
+        ProgressBarBindable::themed(Widget::MessageBar)
+            .bind_progress(move |summary: &GroupSummary| summary.progress(&label))
+    }
 
-        ProgressBarBindable::new_standalone(style).bind_message(updater)
+    /// A group summary like `"✔ 50 tasks done in 1m12s"`, see
+    /// [`crate::ProgressBarTreeContainer::with_summary`].
+    pub fn summary_done(noun: impl Into<String>) -> ProgressBarBindable<GroupSummary> {
+        let noun = noun.into();
+        ProgressBarBindable::themed(Widget::Spinner)
+            .bind_message(move |summary: &GroupSummary| summary.done_message(&noun))
     }
 
     pub fn progress_bar_default<V>(
         updater: impl Fn(&V) -> DisplayState<ProgressBarState> + 'static + Send,
     ) -> ProgressBarBindable<V> {
-        ProgressBarBindable::new_standalone(ProgressStyle::default_bar()).bind_progress(updater)
+        ProgressBarBindable::themed(Widget::Bar).bind_progress(updater)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tree::panel, *};
+    use crate::{ProgressBarTree, TerminalCapabilities};
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    #[test]
+    fn test_panel() {
+        struct Stats {
+            files: usize,
+            throughput: Option<u64>,
+            errors: usize,
+        }
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                panel()
+                    .field("Files", |stats: &Stats| {
+                        DisplayState::Active(stats.files.to_string())
+                    })
+                    .field("Throughput", |stats: &Stats| {
+                        DisplayState::Active(
+                            stats
+                                .throughput
+                                .map(|mb| format!("{mb} MB/s"))
+                                .unwrap_or_default(),
+                        )
+                    })
+                    .field("Errors", |stats: &Stats| {
+                        DisplayState::Active(stats.errors.to_string())
+                    })
+                    .into(),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        tree.tick(&Stats {
+            files: 1234,
+            throughput: Some(42),
+            errors: 0,
+        });
+        assert_eq!(
+            term.contents(),
+            "Files:      1234\nThroughput: 42 MB/s\nErrors:     0"
+        );
+
+        // The empty throughput is hidden and the other labels line up without it
+        tree.tick(&Stats {
+            files: 1300,
+            throughput: None,
+            errors: 2,
+        });
+        assert_eq!(term.contents(), "Files:  1300\nErrors: 2");
     }
 }
@@ -0,0 +1,197 @@
+use crate::{extension::BarExtension, render::draw_bar, theme::Theme};
+use indicatif::{ProgressState, ProgressStyle};
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+/// How a bar draws the recent values of a number taken from the model, see
+/// [`crate::ProgressBarBindable::with_history`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct History {
+    /// How many ticks of values are kept
+    pub window: usize,
+    pub chart: Chart,
+    pub scale: Scale,
+}
+
+impl History {
+    /// A sparkline of the last `window` values, scaled from zero.
+    pub fn sparkline(window: usize) -> Self {
+        Self {
+            window,
+            chart: Chart::Sparkline,
+            scale: Scale::default(),
+        }
+    }
+
+    /// A bar `width` wide showing the newest value against the last `window`, scaled from zero.
+    pub fn bar(window: usize, width: usize) -> Self {
+        Self {
+            window,
+            chart: Chart::Bar(width),
+            scale: Scale::default(),
+        }
+    }
+
+    pub fn with_scale(mut self, scale: Scale) -> Self {
+        self.scale = scale;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chart {
+    /// One cell per value, drawn with the theme's `spark_chars`, e.g. `"▁▂▃▅▇"`. Right aligned
+    /// until the window fills so it doesn't change width.
+    Sparkline,
+    /// A bar of the given width drawn with the theme's `bar_chars`, full at the top of the scale
+    Bar(usize),
+}
+
+/// The range of values a [`Chart`] is drawn across. Values outside it are clamped.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Scale {
+    /// From zero to the largest value in the window
+    #[default]
+    FromZero,
+    /// From the smallest to the largest value in the window, to show small changes
+    Range,
+    /// From zero to the given maximum, e.g. a known link speed
+    Fixed(f64),
+}
+
+impl Scale {
+    fn bounds(self, values: &VecDeque<f64>) -> (f64, f64) {
+        let max = values.iter().copied().fold(f64::MIN, f64::max);
+        match self {
+            Scale::FromZero => (0.0, max),
+            Scale::Range => (values.iter().copied().fold(f64::MAX, f64::min), max),
+            Scale::Fixed(max) => (0.0, max),
+        }
+    }
+}
+
+/// A number taken from the model on each tick, along with its last few values, drawn by the
+/// template key it's bound to.
+pub(crate) struct BoundHistory<S> {
+    key: &'static str,
+    history: History,
+    sample: S,
+    values: Arc<Mutex<VecDeque<f64>>>,
+}
+
+impl<S> BoundHistory<S> {
+    pub(crate) fn new(key: &'static str, history: History, sample: S) -> Self {
+        Self {
+            key,
+            history,
+            sample,
+            values: Arc::new(Mutex::new(VecDeque::with_capacity(history.window))),
+        }
+    }
+
+    /// The template key drawing the chart with `theme`'s characters.
+    fn draw(
+        &self,
+        theme: &Theme,
+    ) -> impl Fn(&ProgressState, &mut dyn fmt::Write) + Clone + Send + Sync + 'static {
+        let (history, values) = (self.history, self.values.clone());
+        let (spark_chars, bar_chars) = (theme.spark_chars.clone(), theme.bar_chars.clone());
+        move |_: &ProgressState, w: &mut dyn fmt::Write| {
+            let values = values.lock().unwrap();
+            let (low, high) = history.scale.bounds(&values);
+            let fraction = |value: f64| {
+                if high > low {
+                    ((value - low) / (high - low)).clamp(0.0, 1.0)
+                } else {
+                    0.0
+                }
+            };
+            let chart = match history.chart {
+                Chart::Sparkline => {
+                    let levels: Vec<char> = spark_chars.chars().collect();
+                    let top = levels.len().saturating_sub(1) as f64;
+                    let blank = history.window.saturating_sub(values.len());
+                    let cells = values.iter().filter_map(|value| {
+                        levels
+                            .get((fraction(*value) * top).round() as usize)
+                            .copied()
+                    });
+                    " ".repeat(blank).chars().chain(cells).collect()
+                }
+                Chart::Bar(width) => draw_bar(
+                    values.back().map_or(0.0, |value| fraction(*value)),
+                    width,
+                    &bar_chars,
+                ),
+            };
+            let _ = w.write_str(&chart);
+        }
+    }
+}
+
+impl<V, S: Fn(&V) -> f64 + Send> BarExtension<V> for BoundHistory<S> {
+    /// Takes the value from the model, dropping the oldest once the window is full.
+    fn record(&self, model: &V) {
+        let value = (self.sample)(model);
+        let mut values = self.values.lock().unwrap();
+        if values.len() == self.history.window {
+            values.pop_front();
+        }
+        if self.history.window > 0 {
+            values.push_back(value);
+        }
+    }
+
+    fn style(&self, style: ProgressStyle, theme: &Theme) -> ProgressStyle {
+        style.with_key(self.key, self.draw(theme))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DisplayState, History, ProgressBarTree, Scale, TerminalCapabilities, helpers::widgets::*,
+    };
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    #[test]
+    fn test_history() {
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                chart(
+                    |rate: &f64| DisplayState::Active(format!("{rate} MB/s")),
+                    |rate| *rate,
+                    History::sparkline(4),
+                )
+                .into(),
+                from_template_str("[{rate}]")
+                    .with_history("rate", |rate: &f64| *rate, History::bar(4, 5))
+                    .bind_message(|_| DisplayState::Active(String::new()))
+                    .into(),
+                from_template_str("[{rate}]")
+                    .with_history(
+                        "rate",
+                        |rate: &f64| *rate,
+                        History::sparkline(3).with_scale(Scale::Range),
+                    )
+                    .bind_message(|_| DisplayState::Active(String::new()))
+                    .into(),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        tree.tick(&5.0);
+        assert_eq!(term.contents(), "5 MB/s    #\n[#####]\n[  _]");
+
+        // The oldest value drops out of the window, leaving 10 as the top of the scale
+        for rate in [10.0, 0.0, 2.0, 6.0] {
+            tree.tick(&rate);
+        }
+        assert_eq!(term.contents(), "6 MB/s #_.=\n[###>-]\n[_-#]");
+    }
+}
@@ -0,0 +1,205 @@
+use crate::{DisplayState, LeafUpdate, ProgressBarState, ProgressSink};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    io::Write,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A [`ProgressSink`] that writes one JSON object per line to `writer` whenever a leaf's state
+/// changes between ticks.
+///
+/// Each line looks like:
+///
+/// ```json
+/// {"id":"0.2","path":[0,2],"state":"active","message":"Downloading","prefix":null,"position":5,"length":10,"timestamp_ms":1700000000000,"started_ms":1699999990000,"finished_ms":null}
+/// ```
+///
+/// `state` is one of `not_started`, `active`, `finished`, `failed` or `hidden`. Timestamps are
+/// milliseconds since the unix epoch; `started_ms` and `finished_ms` record when the leaf first
+/// became active or finished.
+pub struct JsonLinesSink<W: Write + Send> {
+    writer: W,
+    leaves: HashMap<Vec<usize>, LeafRecord>,
+}
+
+struct LeafRecord {
+    state: DisplayState<ProgressBarState>,
+    started_ms: Option<u64>,
+    finished_ms: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct LeafEvent<'a> {
+    id: String,
+    path: &'a [usize],
+    state: &'static str,
+    message: Option<&'a str>,
+    prefix: Option<&'a str>,
+    position: Option<u64>,
+    length: Option<u64>,
+    timestamp_ms: u64,
+    started_ms: Option<u64>,
+    finished_ms: Option<u64>,
+}
+
+impl<W: Write + Send> JsonLinesSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            leaves: HashMap::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+impl<W: Write + Send> ProgressSink for JsonLinesSink<W> {
+    fn leaf_ticked(&mut self, leaf: &LeafUpdate<'_>) {
+        if let Some(record) = self.leaves.get(leaf.path)
+            && &record.state == leaf.state
+        {
+            return;
+        }
+
+        let now = now_ms();
+        let record = self
+            .leaves
+            .entry(leaf.path.to_vec())
+            .or_insert_with(|| LeafRecord {
+                state: DisplayState::NotStarted,
+                started_ms: None,
+                finished_ms: None,
+            });
+        record.state = leaf.state.clone();
+
+        let (state, progress) = match leaf.state {
+            DisplayState::NotStarted => ("not_started", None),
+            DisplayState::Active(progress) => ("active", Some(progress)),
+            DisplayState::Finished(progress) => ("finished", Some(progress)),
+            DisplayState::Failed(progress) => ("failed", Some(progress)),
+            DisplayState::FinishedAndHidden => ("hidden", None),
+        };
+        if matches!(leaf.state, DisplayState::Active(_)) {
+            record.started_ms.get_or_insert(now);
+        }
+        if leaf.state.is_finished() {
+            record.finished_ms.get_or_insert(now);
+        }
+
+        let position_and_len = progress.and_then(|p| p.position_and_len());
+        let event = LeafEvent {
+            id: leaf.stable_id(),
+            path: leaf.path,
+            state,
+            message: progress.and_then(|p| p.message()),
+            prefix: progress.and_then(|p| p.prefix()),
+            position: position_and_len.map(|(pos, _)| pos),
+            length: position_and_len.map(|(_, len)| len),
+            timestamp_ms: now,
+            started_ms: record.started_ms,
+            finished_ms: record.finished_ms,
+        };
+
+        // Progress output is best effort, the same as indicatif's own draw targets
+        if serde_json::to_writer(&mut self.writer, &event).is_ok() {
+            let _ = self.writer.write_all(b"\n");
+        }
+    }
+
+    fn tick_finished(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ProgressBarTree,
+        helpers::{tree::group, widgets::*},
+    };
+    use indicatif::{MultiProgress, ProgressDrawTarget};
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Clone, Default)]
+    struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl SharedBuffer {
+        fn take_lines(&self) -> Vec<serde_json::Value> {
+            let bytes = std::mem::take(&mut *self.0.lock().unwrap());
+            String::from_utf8(bytes)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_json_lines_only_emits_changes() {
+        let buffer = SharedBuffer::default();
+        let tree = ProgressBarTree::<u64>::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden()),
+            vec![
+                message_static("Header").into(),
+                group(vec![
+                    progress_bar_default(|v: &u64| {
+                        if *v < 10 {
+                            DisplayState::Active(ProgressBarState::new(None, None, *v, 10))
+                        } else {
+                            DisplayState::Finished(ProgressBarState::new(None, None, 10, 10))
+                        }
+                    })
+                    .with_id("download")
+                    .into(),
+                ]),
+            ],
+        )
+        .with_sink(JsonLinesSink::new(buffer.clone()));
+
+        tree.tick(&5);
+        let lines = buffer.take_lines();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["id"], "0");
+        assert_eq!(lines[0]["state"], "finished");
+        assert_eq!(lines[0]["message"], "Header");
+        assert_eq!(lines[1]["id"], "download");
+        assert_eq!(lines[1]["path"], serde_json::json!([1, 0]));
+        assert_eq!(lines[1]["state"], "active");
+        assert_eq!(lines[1]["position"], 5);
+        assert_eq!(lines[1]["length"], 10);
+        assert!(lines[1]["started_ms"].is_u64());
+        assert!(lines[1]["finished_ms"].is_null());
+
+        // Nothing changed, so nothing is written
+        tree.tick(&5);
+        assert!(buffer.take_lines().is_empty());
+
+        tree.tick(&10);
+        let lines = buffer.take_lines();
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0]["id"], "download");
+        assert_eq!(lines[0]["state"], "finished");
+        assert!(lines[0]["finished_ms"].is_u64());
+    }
+}
@@ -0,0 +1,478 @@
+use crate::{DisplayState, ProgressBarBindable, ProgressBarState, ProgressBarTreeContainer};
+use indicatif::{ProgressStyle, style::TemplateError};
+use serde::Deserialize;
+use std::{collections::HashMap, fmt, sync::Arc};
+
+/// A tree layout that can be loaded from any format serde supports, so users can change how
+/// progress looks without recompiling. Leaves refer to bindings and display conditions by name,
+/// which the application supplies through [`Bindings`].
+///
+/// ```
+/// use declaratif::{Bindings, DisplayState, LayoutConfig, ProgressBarTree};
+///
+/// struct Model {
+///     downloaded: u64,
+///     verbose: bool,
+/// }
+///
+/// let layout: LayoutConfig = toml::from_str(r#"
+///     [styles.bar]
+///     template = "{prefix} [{bar:40}] {pos}/{len}"
+///     progress_chars = "=> "
+///     finish_template = "{prefix} done"
+///
+///     [[nodes]]
+///     type = "leaf"
+///     style = "bar"
+///     prefix = "Downloading"
+///     bind = "download"
+///
+///     [[nodes]]
+///     type = "group"
+///     when = "verbose"
+///     children = [
+///         { type = "leaf", message = "Extra detail" },
+///     ]
+/// "#).unwrap();
+///
+/// let bindings = Bindings::new()
+///     .progress("download", |m: &Model| {
+///         DisplayState::Active(declaratif::ProgressBarState::new(None, None, m.downloaded, 100))
+///     })
+///     .predicate("verbose", |m: &Model| m.verbose);
+///
+/// let tree = ProgressBarTree::new(
+///     indicatif::MultiProgress::new(),
+///     layout.build(&bindings).unwrap(),
+/// );
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LayoutConfig {
+    /// Named styles that leaves can refer to with `style = "<name>"`
+    #[serde(default)]
+    pub styles: HashMap<String, StyleConfig>,
+    #[serde(default)]
+    pub nodes: Vec<NodeConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum NodeConfig {
+    Leaf(Box<LeafConfig>),
+    Group {
+        /// Name of a predicate; the group is only shown while it returns true
+        when: Option<String>,
+        #[serde(default)]
+        children: Vec<NodeConfig>,
+    },
+}
+
+/// A single bar. The style fields are the same as [`StyleConfig`]'s; they're repeated here rather
+/// than flattened in so that misspelt keys are rejected.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LeafConfig {
+    /// Name of a named style, which any style fields on the leaf itself override
+    pub style: Option<String>,
+    pub template: Option<String>,
+    pub finish_template: Option<String>,
+    pub failed_template: Option<String>,
+    pub tick_chars: Option<String>,
+    pub progress_chars: Option<String>,
+    /// Name of a binding. Leaves without one show their static message once and finish.
+    pub bind: Option<String>,
+    pub message: Option<String>,
+    pub prefix: Option<String>,
+    pub id: Option<String>,
+    /// Name of a predicate; the leaf is only shown while it returns true
+    pub when: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StyleConfig {
+    pub template: Option<String>,
+    pub finish_template: Option<String>,
+    pub failed_template: Option<String>,
+    pub tick_chars: Option<String>,
+    pub progress_chars: Option<String>,
+}
+
+impl LeafConfig {
+    fn style_overrides(&self) -> StyleConfig {
+        StyleConfig {
+            template: self.template.clone(),
+            finish_template: self.finish_template.clone(),
+            failed_template: self.failed_template.clone(),
+            tick_chars: self.tick_chars.clone(),
+            progress_chars: self.progress_chars.clone(),
+        }
+    }
+}
+
+impl StyleConfig {
+    fn or(&self, fallback: &StyleConfig) -> StyleConfig {
+        StyleConfig {
+            template: self.template.clone().or(fallback.template.clone()),
+            finish_template: self
+                .finish_template
+                .clone()
+                .or(fallback.finish_template.clone()),
+            failed_template: self
+                .failed_template
+                .clone()
+                .or(fallback.failed_template.clone()),
+            tick_chars: self.tick_chars.clone().or(fallback.tick_chars.clone()),
+            progress_chars: self
+                .progress_chars
+                .clone()
+                .or(fallback.progress_chars.clone()),
+        }
+    }
+
+    fn build_style(&self, template: &str) -> Result<ProgressStyle, LayoutError> {
+        let mut style =
+            ProgressStyle::with_template(template).map_err(|error| LayoutError::Template {
+                template: template.to_string(),
+                error,
+            })?;
+
+        // indicatif panics on fewer than two characters, so check them here
+        if let Some(chars) = &self.tick_chars {
+            if chars.chars().count() < 2 {
+                return Err(LayoutError::Chars(chars.clone()));
+            }
+            style = style.tick_chars(chars);
+        }
+        if let Some(chars) = &self.progress_chars {
+            if chars.chars().count() < 2 {
+                return Err(LayoutError::Chars(chars.clone()));
+            }
+            style = style.progress_chars(chars);
+        }
+        Ok(style)
+    }
+}
+
+#[derive(Debug)]
+pub enum LayoutError {
+    UnknownBinding(String),
+    UnknownPredicate(String),
+    UnknownStyle(String),
+    Template {
+        template: String,
+        error: TemplateError,
+    },
+    /// Tick or progress chars need at least two characters
+    Chars(String),
+}
+
+impl fmt::Display for LayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutError::UnknownBinding(name) => write!(f, "unknown binding `{name}`"),
+            LayoutError::UnknownPredicate(name) => write!(f, "unknown predicate `{name}`"),
+            LayoutError::UnknownStyle(name) => write!(f, "unknown style `{name}`"),
+            LayoutError::Template { template, error } => {
+                write!(f, "invalid template `{template}`: {error}")
+            }
+            LayoutError::Chars(chars) => {
+                write!(f, "`{chars}` needs at least two characters")
+            }
+        }
+    }
+}
+
+impl std::error::Error for LayoutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LayoutError::Template { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+type SharedFn<V, O> = Arc<dyn Fn(&V) -> O + Send + Sync>;
+
+enum Binding<V> {
+    Message(SharedFn<V, DisplayState<String>>),
+    Progress(SharedFn<V, DisplayState<ProgressBarState>>),
+    DisplayState(SharedFn<V, DisplayState<()>>),
+}
+
+/// The named bindings and predicates a [`LayoutConfig`] can refer to.
+pub struct Bindings<V> {
+    bindings: HashMap<String, Binding<V>>,
+    predicates: HashMap<String, SharedFn<V, bool>>,
+}
+
+impl<V> Default for Bindings<V> {
+    fn default() -> Self {
+        Self {
+            bindings: HashMap::new(),
+            predicates: HashMap::new(),
+        }
+    }
+}
+
+impl<V> Bindings<V> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn message(
+        mut self,
+        name: impl Into<String>,
+        updater: impl Fn(&V) -> DisplayState<String> + 'static + Send + Sync,
+    ) -> Self {
+        self.bindings
+            .insert(name.into(), Binding::Message(Arc::new(updater)));
+        self
+    }
+
+    pub fn progress(
+        mut self,
+        name: impl Into<String>,
+        updater: impl Fn(&V) -> DisplayState<ProgressBarState> + 'static + Send + Sync,
+    ) -> Self {
+        self.bindings
+            .insert(name.into(), Binding::Progress(Arc::new(updater)));
+        self
+    }
+
+    pub fn display_state(
+        mut self,
+        name: impl Into<String>,
+        updater: impl Fn(&V) -> DisplayState<()> + 'static + Send + Sync,
+    ) -> Self {
+        self.bindings
+            .insert(name.into(), Binding::DisplayState(Arc::new(updater)));
+        self
+    }
+
+    pub fn predicate(
+        mut self,
+        name: impl Into<String>,
+        predicate: impl Fn(&V) -> bool + 'static + Send + Sync,
+    ) -> Self {
+        self.predicates.insert(name.into(), Arc::new(predicate));
+        self
+    }
+}
+
+impl LayoutConfig {
+    /// Builds the children of a [`crate::ProgressBarTree`], failing on the first reference to a
+    /// binding, predicate or style that doesn't exist, or an invalid template.
+    pub fn build<V: Send + Sync + 'static>(
+        &self,
+        bindings: &Bindings<V>,
+    ) -> Result<Vec<ProgressBarTreeContainer<V>>, LayoutError> {
+        self.nodes
+            .iter()
+            .map(|node| self.build_node(node, bindings))
+            .collect()
+    }
+
+    fn build_node<V: Send + Sync + 'static>(
+        &self,
+        node: &NodeConfig,
+        bindings: &Bindings<V>,
+    ) -> Result<ProgressBarTreeContainer<V>, LayoutError> {
+        let (container, when): (ProgressBarTreeContainer<V>, _) = match node {
+            NodeConfig::Leaf(leaf) => (self.build_leaf(leaf, bindings)?.into(), &leaf.when),
+            NodeConfig::Group { when, children } => (
+                children
+                    .iter()
+                    .map(|child| self.build_node(child, bindings))
+                    .collect::<Result<Vec<_>, _>>()?
+                    .into(),
+                when,
+            ),
+        };
+
+        match when {
+            Some(name) => {
+                let predicate = bindings
+                    .predicates
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| LayoutError::UnknownPredicate(name.clone()))?;
+                Ok(container.with_display_condition(Box::new(move |v| predicate(v))))
+            }
+            None => Ok(container),
+        }
+    }
+
+    fn build_leaf<V: Send + Sync + 'static>(
+        &self,
+        leaf: &LeafConfig,
+        bindings: &Bindings<V>,
+    ) -> Result<ProgressBarBindable<V>, LayoutError> {
+        let style = match &leaf.style {
+            Some(name) => {
+                let named = self
+                    .styles
+                    .get(name)
+                    .ok_or_else(|| LayoutError::UnknownStyle(name.clone()))?;
+                leaf.style_overrides().or(named)
+            }
+            None => leaf.style_overrides(),
+        };
+
+        let mut bar = ProgressBarBindable::new(
+            style.build_style(style.template.as_deref().unwrap_or("{msg}"))?,
+        );
+        if let Some(template) = &style.finish_template {
+            bar = bar.with_finish_style(style.build_style(template)?);
+        }
+        if let Some(template) = &style.failed_template {
+            bar = bar.with_failed_style(style.build_style(template)?);
+        }
+        if let Some(message) = &leaf.message {
+            bar = bar.with_static_message(message.clone());
+        }
+        if let Some(prefix) = &leaf.prefix {
+            bar = bar.with_static_prefix(prefix.clone());
+        }
+        if let Some(id) = &leaf.id {
+            bar = bar.with_id(id.clone());
+        }
+
+        let Some(name) = &leaf.bind else {
+            return Ok(bar);
+        };
+        Ok(
+            match bindings
+                .bindings
+                .get(name)
+                .ok_or_else(|| LayoutError::UnknownBinding(name.clone()))?
+            {
+                Binding::Message(updater) => {
+                    let updater = updater.clone();
+                    bar.bind_message(move |v| updater(v))
+                }
+                Binding::Progress(updater) => {
+                    let updater = updater.clone();
+                    bar.bind_progress(move |v| updater(v))
+                }
+                Binding::DisplayState(updater) => {
+                    let updater = updater.clone();
+                    bar.bind_display_state(move |v| updater(v))
+                }
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProgressBarTree;
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    struct Model {
+        step: u64,
+        verbose: bool,
+    }
+
+    fn bindings() -> Bindings<Model> {
+        Bindings::new()
+            .message("status", |m: &Model| {
+                DisplayState::Active(format!("step {}", m.step))
+            })
+            .progress("steps", |m: &Model| {
+                DisplayState::Active(ProgressBarState::new(None, None, m.step, 4))
+            })
+            .predicate("verbose", |m: &Model| m.verbose)
+    }
+
+    #[test]
+    fn test_layout_from_toml() {
+        let layout: LayoutConfig = toml::from_str(
+            r##"
+            [styles.bar]
+            template = "[{bar:4}] {pos}/{len}"
+            progress_chars = "#-"
+
+            [[nodes]]
+            type = "leaf"
+            template = "{prefix}{msg}"
+            prefix = "> "
+            bind = "status"
+
+            [[nodes]]
+            type = "leaf"
+            style = "bar"
+            bind = "steps"
+
+            [[nodes]]
+            type = "group"
+            when = "verbose"
+            children = [
+                { type = "leaf", message = "verbose output" },
+            ]
+            "##,
+        )
+        .unwrap();
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            layout.build(&bindings()).unwrap(),
+        );
+
+        let mut model = Model {
+            step: 2,
+            verbose: false,
+        };
+        tree.tick(&model);
+        assert_eq!(term.contents(), "> step 2\n[##--] 2/4");
+
+        model.verbose = true;
+        tree.tick(&model);
+        assert_eq!(term.contents(), "> step 2\n[##--] 2/4\nverbose output");
+    }
+
+    #[test]
+    fn test_layout_errors() {
+        let build = |source: &str| {
+            toml::from_str::<LayoutConfig>(source)
+                .unwrap()
+                .build(&bindings())
+                .map(|_| ())
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            build("[[nodes]]\ntype = \"leaf\"\nbind = \"missing\""),
+            "unknown binding `missing`"
+        );
+        assert_eq!(
+            build("[[nodes]]\ntype = \"group\"\nwhen = \"missing\""),
+            "unknown predicate `missing`"
+        );
+        assert_eq!(
+            build("[[nodes]]\ntype = \"leaf\"\nstyle = \"missing\""),
+            "unknown style `missing`"
+        );
+        assert_eq!(
+            build("[[nodes]]\ntype = \"leaf\"\ntick_chars = \"x\""),
+            "`x` needs at least two characters"
+        );
+        assert!(
+            build("[[nodes]]\ntype = \"leaf\"\ntemplate = \"{msg:x}\"")
+                .starts_with("invalid template")
+        );
+
+        // Misspelt keys are rejected rather than ignored
+        for source in [
+            "[[nodes]]\ntype = \"leaf\"\nprefx = \"> \"",
+            "[styles.bar]\ntemplat = \"{msg}\"",
+        ] {
+            let error = toml::from_str::<LayoutConfig>(source).unwrap_err();
+            assert!(error.to_string().contains("unknown field"));
+        }
+    }
+}
@@ -1,8 +1,63 @@
+// Lets the derive macros, which refer to `::declaratif`, be used in this crate's own tests
+#[cfg(test)]
+extern crate self as declaratif;
+
+mod dynamic;
+mod extension;
+mod group;
 pub mod helpers;
+mod history;
+#[cfg(feature = "json")]
+mod json_sink;
+#[cfg(feature = "serde")]
+mod layout;
+#[cfg(feature = "log")]
+mod log_bridge;
+mod macros;
 mod multiprogress_bindable;
 mod progress_bar_bindable;
+#[cfg(feature = "json")]
+pub mod protocol;
+mod render;
+mod sink;
+#[cfg(feature = "tracing")]
+mod span_tree;
+mod steps;
+mod summary;
+pub mod table;
+mod tail;
+pub mod tasks;
+mod theme;
+mod timer;
+#[cfg(feature = "tracing")]
+mod tracing_bridge;
 mod tree;
+mod view;
+mod viewport;
+mod writer;
 
 // Re-exports
+#[cfg(feature = "derive")]
+pub use declaratif_derive::ProgressView;
+pub use dynamic::DynamicGroup;
+pub use group::{CollapsePolicy, Columns, GroupOptions, SortOrder, VisiblePriority};
+pub use history::{Chart, History, Scale};
+#[cfg(feature = "json")]
+pub use json_sink::*;
+#[cfg(feature = "serde")]
+pub use layout::*;
+#[cfg(feature = "log")]
+pub use log_bridge::*;
 pub use progress_bar_bindable::*;
+pub use sink::*;
+#[cfg(feature = "tracing")]
+pub use span_tree::*;
+pub use steps::{Step, StepStatus};
+pub use summary::{GroupSummary, format_duration};
+pub use tail::TailOptions;
+pub use theme::*;
+pub use timer::{TimeFormat, Timer};
 pub use tree::*;
+pub use view::*;
+pub use viewport::HeightLimit;
+pub use writer::{LogTail, ProgressWriter};
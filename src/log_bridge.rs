@@ -0,0 +1,91 @@
+use crate::ProgressWriter;
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+use std::{io::Write, sync::Mutex};
+
+/// A [`log`] logger that prints records through a [`ProgressWriter`], so they appear above the
+/// tree (or in a [`crate::LogTail`]) instead of tearing through the bars.
+///
+/// ```no_run
+/// # use declaratif::{ProgressBarTree, ProgressLogger};
+/// # let tree = ProgressBarTree::<()>::new(indicatif::MultiProgress::new(), vec![]);
+/// ProgressLogger::new(tree.writer())
+///     .with_max_level(log::LevelFilter::Info)
+///     .init()
+///     .unwrap();
+/// ```
+pub struct ProgressLogger {
+    writer: Mutex<ProgressWriter>,
+    max_level: LevelFilter,
+}
+
+impl ProgressLogger {
+    pub fn new(writer: ProgressWriter) -> Self {
+        Self {
+            writer: Mutex::new(writer),
+            max_level: LevelFilter::Trace,
+        }
+    }
+
+    pub fn with_max_level(mut self, max_level: LevelFilter) -> Self {
+        self.max_level = max_level;
+        self
+    }
+
+    /// Installs this as the global logger.
+    pub fn init(self) -> Result<(), SetLoggerError> {
+        let max_level = self.max_level;
+        log::set_boxed_logger(Box::new(self))?;
+        log::set_max_level(max_level);
+        Ok(())
+    }
+}
+
+impl Log for ProgressLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.max_level
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let mut writer = self.writer.lock().unwrap();
+        let _ = writeln!(
+            writer,
+            "{:<5} {}: {}",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+    }
+
+    fn flush(&self) {
+        let _ = self.writer.lock().unwrap().flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LogTail;
+    use log::Level;
+
+    #[test]
+    fn test_logger_writes_formatted_records() {
+        let tail = LogTail::new(10);
+        let logger = ProgressLogger::new(tail.writer()).with_max_level(LevelFilter::Info);
+
+        for (level, message) in [(Level::Info, "shown"), (Level::Debug, "filtered")] {
+            logger.log(
+                &Record::builder()
+                    .level(level)
+                    .target("app")
+                    .args(format_args!("{message}"))
+                    .build(),
+            );
+        }
+
+        assert_eq!(tail.last(10), vec!["INFO  app: shown"]);
+    }
+}
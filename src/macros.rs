@@ -0,0 +1,166 @@
+/// Builds the children of a [`crate::ProgressBarTree`] without the `vec![...]` and `.into()`
+/// noise. Expands to a `Vec<ProgressBarTreeContainer<V>>`.
+///
+/// Each comma separated item is one of:
+///
+/// - any expression convertible into a [`crate::ProgressBarTreeContainer`], such as a bar from
+///   the helpers or an existing group
+/// - `group { ... }`, a group of items
+/// - `nested { ... }`, a group drawn one level deeper, see [`crate::helpers::tree::nested`]
+/// - `if condition => { ... }`, a group only shown while `condition(&model)` is true
+/// - `match selector => { value => { ... }, ... }`, showing the case whose value equals
+///   `selector(&model)`, see [`crate::helpers::tree::switch`]
+/// - `for keys => |key| { ... }`, one group per key returned by `keys(&model)`, see
+///   [`crate::helpers::tree::for_each`]. `key` is a reference to the key.
+///
+/// ```
+/// use declaratif::{DisplayState, ProgressBarTree, helpers::widgets::*, tree};
+///
+/// struct Model {
+///     files: Vec<String>,
+///     verbose: bool,
+/// }
+///
+/// let tree = ProgressBarTree::<Model>::new(indicatif::MultiProgress::new(), tree! {
+///     message_static("Copying"),
+///     if |m: &Model| m.verbose => {
+///         message_static("  (verbose)"),
+///     },
+///     for |m: &Model| m.files.clone() => |file| {
+///         message_static(format!("  - {file}")),
+///     },
+///     spacer(),
+/// });
+/// ```
+#[macro_export]
+macro_rules! tree {
+    ($($items:tt)*) => {
+        $crate::__tree_items!(@acc [] $($items)*)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __tree_items {
+    (@acc [$($out:expr,)*]) => {
+        ::std::vec![$($out),*]
+    };
+
+    (@acc [$($out:expr,)*] group { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::__tree_items!(@acc [
+            $($out,)*
+            $crate::helpers::tree::group($crate::tree!($($inner)*)),
+        ] $($($rest)*)?)
+    };
+
+    (@acc [$($out:expr,)*] nested { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::__tree_items!(@acc [
+            $($out,)*
+            $crate::helpers::tree::nested($crate::tree!($($inner)*)),
+        ] $($($rest)*)?)
+    };
+
+    (@acc [$($out:expr,)*] if $condition:expr => { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::__tree_items!(@acc [
+            $($out,)*
+            $crate::helpers::tree::group($crate::tree!($($inner)*))
+                .with_display_condition(::std::boxed::Box::new($condition)),
+        ] $($($rest)*)?)
+    };
+    (@acc [$($out:expr,)*] if $($rest:tt)*) => {
+        ::core::compile_error!("expected `if condition => { ... }`, where `condition` is a `Fn(&V) -> bool`")
+    };
+
+    (@acc [$($out:expr,)*] match $selector:expr => {
+        $($case:expr => { $($inner:tt)* }),* $(,)?
+    } $(, $($rest:tt)*)?) => {
+        $crate::__tree_items!(@acc [
+            $($out,)*
+            $crate::helpers::tree::switch($selector, ::std::vec![
+                $(($case, $crate::helpers::tree::group($crate::tree!($($inner)*)))),*
+            ]),
+        ] $($($rest)*)?)
+    };
+    (@acc [$($out:expr,)*] match $($rest:tt)*) => {
+        ::core::compile_error!("expected `match selector => { value => { ... }, ... }`, where `selector` is a `Fn(&V) -> K`")
+    };
+
+    (@acc [$($out:expr,)*] for $keys:expr => |$key:ident| { $($inner:tt)* } $(, $($rest:tt)*)?) => {
+        $crate::__tree_items!(@acc [
+            $($out,)*
+            $crate::helpers::tree::for_each($keys, move |$key: &_| {
+                $crate::helpers::tree::group($crate::tree!($($inner)*))
+            }),
+        ] $($($rest)*)?)
+    };
+    (@acc [$($out:expr,)*] for $($rest:tt)*) => {
+        ::core::compile_error!("expected `for keys => |key| { ... }`, where `keys` is a `Fn(&V) -> Vec<K>`")
+    };
+
+    (@acc [$($out:expr,)*] $item:expr $(, $($rest:tt)*)?) => {
+        $crate::__tree_items!(@acc [
+            $($out,)*
+            ::core::convert::Into::<$crate::ProgressBarTreeContainer<_>>::into($item),
+        ] $($($rest)*)?)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DisplayState, ProgressBarTree,
+        helpers::{tree::group, widgets::*},
+    };
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    struct Model {
+        mode: u8,
+        show_extra: bool,
+        items: Vec<&'static str>,
+    }
+
+    #[test]
+    fn test_tree_macro() {
+        let term = InMemoryTerm::new(20, 80);
+        let tree = ProgressBarTree::<Model>::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            tree! {
+                message_static("header"),
+                group {
+                    message(|m: &Model| DisplayState::Active(format!("mode {}", m.mode))),
+                },
+                if |m: &Model| m.show_extra => {
+                    message_static("extra"),
+                },
+                match |m: &Model| m.mode => {
+                    0 => { message_static("zero") },
+                    1 => { message_static("one"), message_static("uno") },
+                },
+                for |m: &Model| m.items.clone() => |item| {
+                    message_static(format!("item {item}")),
+                },
+                group(vec![message_static("existing").into()])
+            },
+        );
+
+        let mut model = Model {
+            mode: 0,
+            show_extra: false,
+            items: vec!["a", "b"],
+        };
+        tree.tick(&model);
+        assert_eq!(
+            term.contents(),
+            "header\nmode 0\nzero\nitem a\nitem b\nexisting"
+        );
+
+        model.mode = 1;
+        model.show_extra = true;
+        model.items = vec!["b"];
+        tree.tick(&model);
+        assert_eq!(
+            term.contents(),
+            "header\nmode 1\nextra\none\nuno\nitem b\nexisting"
+        );
+    }
+}
@@ -1,27 +1,46 @@
 use indicatif::{MultiProgress, ProgressBar};
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, HashMap},
+    io,
+    ops::Bound,
     sync::{Arc, RwLock},
 };
 
+/// The position of a bar within a tree, as child indices from the root. Paths order
+/// lexicographically, so bars added under a dynamic group later still sort into the right place.
+pub type BarPath = Vec<usize>;
+
 #[derive(Debug, Clone)]
-pub struct MultiProgressWrapper {
+pub struct MultiProgressWrapper<K: Ord = BarPath> {
     root: MultiProgress,
-    ordered_bars: Arc<RwLock<BTreeMap<usize, ProgressBar>>>,
+    ordered_bars: Arc<RwLock<BTreeMap<K, ProgressBar>>>,
+    // Where the children of sorted groups are drawn, by the group's path then the child's slot
+    positions: Arc<RwLock<HashMap<BarPath, HashMap<usize, usize>>>>,
 }
 
-impl MultiProgressWrapper {
+impl<K: Ord> MultiProgressWrapper<K> {
     pub fn new(root: MultiProgress) -> Self {
         Self {
             root,
             ordered_bars: Arc::new(RwLock::new(BTreeMap::new())),
+            positions: Arc::new(RwLock::new(HashMap::new())),
         }
     }
 
-    pub fn insert_absolute(&self, index_abs: usize, bar: ProgressBar) {
+    pub fn insert_absolute(&self, index_abs: K, bar: ProgressBar) {
         let mut ordered_bars = self.ordered_bars.write().unwrap();
-        // Find the largest element less than index_abs
-        let mut successor_range = ordered_bars.range(index_abs + 1..);
+        self.insert_locked(&mut ordered_bars, index_abs, bar);
+    }
+
+    fn insert_locked(
+        &self,
+        ordered_bars: &mut BTreeMap<K, ProgressBar>,
+        index_abs: K,
+        bar: ProgressBar,
+    ) {
+        // Find the smallest element greater than index_abs
+        let mut successor_range =
+            ordered_bars.range((Bound::Excluded(&index_abs), Bound::Unbounded));
         let bar = if let Some((_index, successor)) = successor_range.next() {
             self.root.insert_before(successor, bar)
         } else {
@@ -32,9 +51,9 @@ impl MultiProgressWrapper {
         ordered_bars.insert(index_abs, bar);
     }
 
-    pub fn remove_at_index(&self, bar_index: usize) {
+    pub fn remove_at_index(&self, bar_index: &K) {
         let mut ordered_bars = self.ordered_bars.write().unwrap();
-        if let Some(bar) = ordered_bars.remove(&bar_index) {
+        if let Some(bar) = ordered_bars.remove(bar_index) {
             // Remove the bar from the MultiProgress
             bar.finish_and_clear();
             self.root.remove(&bar);
@@ -49,13 +68,128 @@ impl MultiProgressWrapper {
         }
     }
 
-    pub fn get_bar_at_index(&self, index: usize) -> Option<ProgressBar> {
+    /// Prints a line above the bars. Does nothing if the draw target is hidden.
+    pub fn println(&self, msg: impl AsRef<str>) -> io::Result<()> {
+        self.root.println(msg)
+    }
+
+    /// Hides the bars while `f` runs, then redraws them.
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        self.root.suspend(f)
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.root.is_hidden()
+    }
+
+    pub fn get_bar_at_index(&self, index: &K) -> Option<ProgressBar> {
         let ordered_bars = self.ordered_bars.read().unwrap();
-        ordered_bars.get(&index).cloned()
+        ordered_bars.get(index).cloned()
+    }
+}
+
+impl MultiProgressWrapper<BarPath> {
+    /// The index a bar at `path` is drawn at. A child of a group placed with
+    /// [`Self::place_children`] is drawn at `[position, slot]` rather than `[slot]`, so indices
+    /// stay unique while bars are moved.
+    pub(crate) fn display_path(&self, path: &[usize]) -> BarPath {
+        let positions = self.positions.read().unwrap();
+        Self::display_path_locked(&positions, path)
+    }
+
+    fn display_path_locked(
+        positions: &HashMap<BarPath, HashMap<usize, usize>>,
+        path: &[usize],
+    ) -> BarPath {
+        let mut display_path = Vec::with_capacity(path.len());
+        for (depth, slot) in path.iter().enumerate() {
+            let position = positions
+                .get(&path[..depth])
+                .and_then(|children| children.get(slot));
+            display_path.extend(position);
+            display_path.push(*slot);
+        }
+        display_path
+    }
+
+    /// Draws the children of the group at `parent` in the order of `slots`, moving any bars
+    /// already drawn rather than recreating them.
+    pub(crate) fn place_children(&self, parent: &[usize], slots: &[usize]) {
+        let mut positions = self.positions.write().unwrap();
+        let placed: HashMap<usize, usize> = slots
+            .iter()
+            .enumerate()
+            .map(|(position, slot)| (*slot, position))
+            .collect();
+        let previous = positions.get(parent);
+        if previous == Some(&placed) {
+            return;
+        }
+
+        // The indices each moved child's bars start with, before and after
+        let display_parent = Self::display_path_locked(&positions, parent);
+        let prefix = |position: Option<usize>, slot: usize| {
+            let mut prefix = display_parent.clone();
+            prefix.extend(position);
+            prefix.push(slot);
+            prefix
+        };
+        let moves: Vec<(BarPath, BarPath)> = placed
+            .iter()
+            .filter_map(|(slot, position)| match previous {
+                // Children without a previous position were added since, so have no bars yet
+                Some(previous) => previous
+                    .get(slot)
+                    .filter(|previous| *previous != position)
+                    .map(|previous| {
+                        (
+                            prefix(Some(*previous), *slot),
+                            prefix(Some(*position), *slot),
+                        )
+                    }),
+                None => Some((prefix(None, *slot), prefix(Some(*position), *slot))),
+            })
+            .collect();
+        positions.insert(parent.to_vec(), placed);
+
+        // Every bar is taken out before any is put back, so none are passed over on the way
+        let mut ordered_bars = self.ordered_bars.write().unwrap();
+        let indices: Vec<(BarPath, BarPath)> = moves
+            .iter()
+            .flat_map(|(from, to)| {
+                ordered_bars
+                    .range(from.clone()..)
+                    .take_while(|(index, _)| index.starts_with(from))
+                    .map(|(index, _)| {
+                        let mut new_index = to.clone();
+                        new_index.extend_from_slice(&index[from.len()..]);
+                        (index.clone(), new_index)
+                    })
+            })
+            .collect();
+        let moved: Vec<(BarPath, ProgressBar)> = indices
+            .into_iter()
+            .map(|(index, new_index)| {
+                let bar = ordered_bars.remove(&index).unwrap();
+                self.root.remove(&bar);
+                (new_index, bar)
+            })
+            .collect();
+        for (index, bar) in moved {
+            self.insert_locked(&mut ordered_bars, index, bar);
+        }
+    }
+
+    /// Forgets where the children of groups at or under `path` were placed, once the child at
+    /// `path` has been removed for good. Its bars must be removed first, as they're found through
+    /// these positions.
+    pub(crate) fn forget_positions(&self, path: &[usize]) {
+        let mut positions = self.positions.write().unwrap();
+        positions.retain(|parent, _| !parent.starts_with(path));
     }
 }
 
-impl From<MultiProgress> for MultiProgressWrapper {
+impl<K: Ord> From<MultiProgress> for MultiProgressWrapper<K> {
     fn from(value: MultiProgress) -> Self {
         MultiProgressWrapper::new(value)
     }
@@ -69,7 +203,7 @@ mod tests {
     #[test]
     fn test_multiprogress_ordering() {
         let root = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
-        let wrapper: MultiProgressWrapper = root.into();
+        let wrapper: MultiProgressWrapper<usize> = root.into();
 
         // Insert in random order
         wrapper.insert_absolute(30, ProgressBar::hidden().with_message("Bar 30"));
@@ -99,8 +233,8 @@ mod tests {
         );
 
         // Remove bars at index 10 and 30
-        wrapper.remove_at_index(10);
-        wrapper.remove_at_index(30);
+        wrapper.remove_at_index(&10);
+        wrapper.remove_at_index(&30);
 
         // Add a new bars at 15 and 0
         wrapper.insert_absolute(15, ProgressBar::hidden().with_message("Bar 15"));
@@ -118,4 +252,58 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_place_children() {
+        let root = MultiProgress::with_draw_target(ProgressDrawTarget::hidden());
+        let wrapper: MultiProgressWrapper = root.into();
+        for (path, message) in [
+            (vec![0], "group"),
+            (vec![0, 0], "a"),
+            (vec![0, 1], "b"),
+            (vec![0, 1, 0], "b1"),
+            (vec![1], "after"),
+        ] {
+            wrapper.insert_absolute(path, ProgressBar::hidden().with_message(message));
+        }
+        let messages = |wrapper: &MultiProgressWrapper| {
+            wrapper
+                .ordered_bars
+                .read()
+                .unwrap()
+                .iter()
+                .map(|(index, bar)| (index.clone(), bar.message()))
+                .collect::<Vec<_>>()
+        };
+
+        // The existing bars move, along with those below them
+        wrapper.place_children(&[0], &[1, 0]);
+        assert_eq!(
+            messages(&wrapper),
+            vec![
+                (vec![0], "group".to_string()),
+                (vec![0, 0, 1], "b".to_string()),
+                (vec![0, 0, 1, 0], "b1".to_string()),
+                (vec![0, 1, 0], "a".to_string()),
+                (vec![1], "after".to_string()),
+            ]
+        );
+        assert_eq!(wrapper.display_path(&[0, 1, 0]), vec![0, 0, 1, 0]);
+
+        wrapper.place_children(&[0], &[0, 1]);
+        assert_eq!(wrapper.display_path(&[0, 1, 0]), vec![0, 1, 1, 0]);
+        assert_eq!(
+            wrapper
+                .get_bar_at_index(&wrapper.display_path(&[0, 0]))
+                .map(|bar| bar.message()),
+            Some("a".to_string())
+        );
+
+        // Removing the group forgets where its children and theirs were placed
+        wrapper.place_children(&[0, 1], &[0]);
+        wrapper.place_children(&[1], &[0]);
+        wrapper.forget_positions(&[0]);
+        let positions = wrapper.positions.read().unwrap();
+        assert_eq!(positions.keys().collect::<Vec<_>>(), vec![&vec![1]]);
+    }
 }
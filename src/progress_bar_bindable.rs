@@ -1,8 +1,19 @@
-use crate::multiprogress_bindable::MultiProgressWrapper;
+use crate::{
+    extension::{BarExtension, Restart},
+    group::{ColumnAlignment, ColumnWidths},
+    history::{BoundHistory, History},
+    multiprogress_bindable::{BarPath, MultiProgressWrapper},
+    theme::{StyleKind, Theme, Widget},
+    timer::{BoundDeadline, BoundTimer, Timer},
+};
 use indicatif::*;
-use std::sync::RwLock;
+use std::{
+    fmt,
+    sync::{Arc, Mutex, RwLock},
+    time::{Duration, Instant},
+};
 
-#[derive(Default)]
+#[derive(Default, Debug, Clone, PartialEq)]
 pub struct ProgressBarState {
     message: Option<String>,
     prefix: Option<String>,
@@ -22,12 +33,36 @@ impl ProgressBarState {
             position_and_len: Some((position, length)),
         }
     }
+
+    /// A state without a position, for spinners and plain messages.
+    pub fn from_message(message: impl Into<String>) -> Self {
+        ProgressBarState {
+            message: Some(message.into()),
+            prefix: None,
+            position_and_len: None,
+        }
+    }
+
+    pub fn message(&self) -> Option<&str> {
+        self.message.as_deref()
+    }
+
+    pub fn prefix(&self) -> Option<&str> {
+        self.prefix.as_deref()
+    }
+
+    pub fn position_and_len(&self) -> Option<(u64, u64)> {
+        self.position_and_len
+    }
 }
 
+#[derive(Debug, Clone, PartialEq)]
 pub enum DisplayState<V> {
     NotStarted,
     Active(V),
     Finished(V),
+    /// Finished unsuccessfully. Rendered like `Finished`, but with the failed style if one is set.
+    Failed(V),
     FinishedAndHidden,
 }
 
@@ -35,32 +70,53 @@ impl<V> DisplayState<V> {
     pub fn is_finished(&self) -> bool {
         matches!(
             self,
-            DisplayState::Finished(_) | DisplayState::FinishedAndHidden
+            DisplayState::Finished(_) | DisplayState::Failed(_) | DisplayState::FinishedAndHidden
         )
     }
 
-    pub fn map<O>(self, f: impl FnOnce(V) -> O + 'static) -> DisplayState<O> {
+    pub fn map<O>(self, f: impl FnOnce(V) -> O) -> DisplayState<O> {
         match self {
             DisplayState::NotStarted => DisplayState::NotStarted,
             DisplayState::Active(v) => DisplayState::Active(f(v)),
             DisplayState::Finished(v) => DisplayState::Finished(f(v)),
+            DisplayState::Failed(v) => DisplayState::Failed(f(v)),
             DisplayState::FinishedAndHidden => DisplayState::FinishedAndHidden,
         }
     }
 }
 
+/// Where a bar draws itself when ticked directly rather than through a
+/// [`crate::ProgressBarTree`]. Bars placed in a tree are always drawn by the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BindingTarget {
+    /// Draws nothing outside a tree
+    #[default]
+    Unbound,
+    /// Creates and draws its own `ProgressBar`
+    Standalone,
+}
+
 enum ProgressBarWrapper {
     Unbound,
     Standalone(RwLock<Option<ProgressBar>>),
-    MultiProgress(MultiProgressWrapper, usize),
+    MultiProgress(MultiProgressWrapper, BarPath),
 }
 
 impl ProgressBarWrapper {
+    fn for_target(target: BindingTarget) -> Self {
+        match target {
+            BindingTarget::Unbound => Self::Unbound,
+            BindingTarget::Standalone => Self::Standalone(RwLock::new(None)),
+        }
+    }
+
     fn is_created(&self) -> bool {
         match self {
             Self::Unbound => false,
             Self::Standalone(bar_lock) => bar_lock.read().unwrap().is_some(),
-            Self::MultiProgress(wrapper, index) => wrapper.get_bar_at_index(*index).is_some(),
+            Self::MultiProgress(wrapper, path) => wrapper
+                .get_bar_at_index(&wrapper.display_path(path))
+                .is_some(),
         }
     }
 
@@ -73,10 +129,10 @@ impl ProgressBarWrapper {
                 .read()
                 .unwrap()
                 .as_ref()
-                .map_or(true, |bar| bar.is_finished()),
-            Self::MultiProgress(wrapper, index) => wrapper
-                .get_bar_at_index(*index)
-                .map_or(true, |bar| bar.is_finished()),
+                .is_none_or(|bar| bar.is_finished()),
+            Self::MultiProgress(wrapper, path) => wrapper
+                .get_bar_at_index(&wrapper.display_path(path))
+                .is_none_or(|bar| bar.is_finished()),
         }
     }
 
@@ -89,8 +145,8 @@ impl ProgressBarWrapper {
                     progress_bar.finish_and_clear();
                 }
             }
-            Self::MultiProgress(wrapper, index) => {
-                wrapper.remove_at_index(*index);
+            Self::MultiProgress(wrapper, path) => {
+                wrapper.remove_at_index(&wrapper.display_path(path));
             }
         }
     }
@@ -107,12 +163,13 @@ impl ProgressBarWrapper {
                     bar_option.clone()
                 }
             }
-            Self::MultiProgress(wrapper, index) => {
-                if let Some(bar) = wrapper.get_bar_at_index(*index) {
+            Self::MultiProgress(wrapper, path) => {
+                let index = wrapper.display_path(path);
+                if let Some(bar) = wrapper.get_bar_at_index(&index) {
                     Some(bar)
                 } else {
                     let bar = ProgressBar::no_length();
-                    wrapper.insert_absolute(*index, bar.clone());
+                    wrapper.insert_absolute(index, bar.clone());
                     Some(bar)
                 }
             }
@@ -124,7 +181,9 @@ impl ProgressBarWrapper {
         match self {
             Self::Unbound => None,
             Self::Standalone(lock) => lock.read().unwrap().clone(),
-            Self::MultiProgress(wrapper, index) => wrapper.get_bar_at_index(*index),
+            Self::MultiProgress(wrapper, path) => {
+                wrapper.get_bar_at_index(&wrapper.display_path(path))
+            }
         }
     }
 }
@@ -132,50 +191,149 @@ impl ProgressBarWrapper {
 type ProgressBarUpdater<V> = Box<dyn Fn(&V) -> DisplayState<ProgressBarState> + Send>;
 pub struct ProgressBarBindable<V> {
     progress_bar: ProgressBarWrapper,
-    base_style: ProgressStyle,
+    widget: Widget,
+    theme: Arc<Theme>,
+    indent: usize,
+    // Indentation worked out by the tree on each tick, drawn by the `{indent}` key
+    line_prefix: Arc<RwLock<String>>,
+    // Widths shared with the bar's siblings, see `GroupOptions::columns`
+    columns: ColumnAlignment,
+    // Styles set explicitly, which take priority over the theme
+    style: Option<ProgressStyle>,
     finish_style: Option<ProgressStyle>,
+    failed_style: Option<ProgressStyle>,
+    tick_chars: Option<String>,
+    // The styles actually used, recomputed whenever any of the above changes
+    resolved: ResolvedStyles,
+    // The style the bar was last given, so it's only set again when that changes
+    applied: Mutex<Option<AppliedStyle>>,
     static_prefix: Option<String>,
     static_message: Option<String>,
+    id: Option<String>,
     updater: Option<ProgressBarUpdater<V>>,
+    // What each widget drawn with the bar adds to it, e.g. a chart's recent values
+    extensions: Vec<Box<dyn BarExtension<V>>>,
+    // Redraws the bar between ticks while it's active
+    steady_tick: Option<Duration>,
+    // The last resolved state, so trees can rank recently updated and finished bars higher
+    history: Mutex<StateHistory>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct AppliedStyle {
+    pending: bool,
+    aligned: bool,
+}
+
+#[derive(Default)]
+struct StateHistory {
+    last: Option<DisplayState<ProgressBarState>>,
+    info: BarInfo,
+}
+
+/// When a bar's resolved state last changed, and when it finished or failed, along with anything
+/// else the tree needs to know to lay it out.
+#[derive(Debug, Clone, Copy, Default)]
+pub(crate) struct BarInfo {
+    pub(crate) updated_at: Option<Instant>,
+    pub(crate) finished_at: Option<Instant>,
+    // The bar draws a line while not started, e.g. a checklist step waiting to run
+    pub(crate) drawn_pending: bool,
+    // The bar isn't bound to the model, e.g. a static message, so isn't counted as work done
+    pub(crate) is_static: bool,
+}
+
+pub(crate) struct ResolvedStyles {
+    base: ProgressStyle,
+    pending: Option<ProgressStyle>,
+    finish: Option<ProgressStyle>,
+    failed: Option<ProgressStyle>,
+}
+
+impl ResolvedStyles {
+    pub(crate) fn map(&self, f: impl Fn(&ProgressStyle) -> ProgressStyle) -> Self {
+        Self {
+            base: f(&self.base),
+            pending: self.pending.as_ref().map(&f),
+            finish: self.finish.as_ref().map(&f),
+            failed: self.failed.as_ref().map(&f),
+        }
+    }
+}
+
+impl Default for ResolvedStyles {
+    fn default() -> Self {
+        Self {
+            base: ProgressStyle::default_bar(),
+            pending: None,
+            finish: None,
+            failed: None,
+        }
+    }
 }
 
 impl<V> ProgressBarBindable<V> {
     // == Constructors and modifiers
     pub fn new(style: ProgressStyle) -> Self {
-        ProgressBarBindable {
-            progress_bar: ProgressBarWrapper::Unbound,
-            base_style: style,
-            finish_style: None,
-            static_prefix: None,
-            static_message: None,
-            updater: None,
-        }
+        Self::from_parts(ProgressBarWrapper::Unbound, Widget::Custom, Some(style))
     }
 
     pub fn new_standalone(style: ProgressStyle) -> Self {
-        ProgressBarBindable {
-            progress_bar: ProgressBarWrapper::Standalone(RwLock::new(None)),
-            base_style: style,
-            finish_style: None,
-            static_prefix: None,
-            static_message: None,
-            updater: None,
-        }
+        Self::new(style).with_target(BindingTarget::Standalone)
     }
 
     pub fn new_multi_progress(
         style: ProgressStyle,
         multiprogress: MultiProgressWrapper,
-        index: usize,
+        index: BarPath,
     ) -> Self {
-        ProgressBarBindable {
-            progress_bar: ProgressBarWrapper::MultiProgress(multiprogress, index),
-            base_style: style,
+        Self::from_parts(
+            ProgressBarWrapper::MultiProgress(multiprogress, index),
+            Widget::Custom,
+            Some(style),
+        )
+    }
+
+    /// A bar styled by the tree's [`Theme`], see [`crate::ProgressBarTree::with_theme`].
+    pub fn themed(widget: Widget) -> Self {
+        Self::from_parts(ProgressBarWrapper::Unbound, widget, None)
+    }
+
+    fn from_parts(
+        progress_bar: ProgressBarWrapper,
+        widget: Widget,
+        style: Option<ProgressStyle>,
+    ) -> Self {
+        let mut bar = ProgressBarBindable {
+            progress_bar,
+            widget,
+            theme: Arc::new(Theme::default()),
+            indent: 0,
+            line_prefix: Arc::new(RwLock::new(String::new())),
+            columns: ColumnAlignment::default(),
+            style,
             finish_style: None,
+            failed_style: None,
+            tick_chars: None,
+            resolved: ResolvedStyles::default(),
+            applied: Mutex::new(None),
             static_prefix: None,
             static_message: None,
+            id: None,
             updater: None,
-        }
+            extensions: Vec::new(),
+            steady_tick: None,
+            history: Mutex::new(StateHistory::default()),
+        };
+        bar.restyle();
+        bar
+    }
+
+    /// Sets where the bar draws itself outside a tree, replacing any bar already drawn.
+    pub fn with_target(mut self, target: BindingTarget) -> Self {
+        self.progress_bar.remove();
+        self.progress_bar = ProgressBarWrapper::for_target(target);
+        self
     }
 
     pub fn bind_message(
@@ -216,6 +374,59 @@ impl<V> ProgressBarBindable<V> {
         self
     }
 
+    /// Takes a number from the model on each tick and draws its recent values as a chart in the
+    /// `{key}` template key, e.g. `{throughput}`. Themed bars use the `{history}` key, see
+    /// [`Widget::Chart`].
+    pub fn with_history(
+        self,
+        key: &'static str,
+        sample: impl Fn(&V) -> f64 + 'static + Send,
+        history: History,
+    ) -> Self {
+        self.with_extension(BoundHistory::new(key, history, sample))
+    }
+
+    /// Takes an instant from the model on each tick and draws the time until or since it in the
+    /// `{key}` template key, measured whenever the bar is drawn. Themed bars use the `{timer}` key,
+    /// see [`Widget::Timer`]. Combine with [`Self::with_steady_tick`] to keep the time counting
+    /// between ticks.
+    pub fn with_timer(
+        self,
+        key: &'static str,
+        instant: impl Fn(&V) -> Option<Instant> + 'static + Send,
+        timer: Timer,
+    ) -> Self {
+        self.with_extension(BoundTimer::new(key, timer, instant))
+    }
+
+    /// Finishes the bar once the instant taken from the model on each tick has passed, even if the
+    /// model isn't ticked then, e.g. a countdown reaching zero. The binding should finish the bar
+    /// too, so it's counted as finished from the next tick.
+    pub fn with_deadline(self, deadline: impl Fn(&V) -> Option<Instant> + 'static + Send) -> Self {
+        self.with_extension(BoundDeadline::new(deadline))
+    }
+
+    /// Lets the bar become active again once it's finished, e.g. a countdown that's set again or a
+    /// checklist step that's retried, starting its elapsed time and ETA over. Otherwise a finished
+    /// bar stays drawn as finished.
+    pub fn restartable(self) -> Self {
+        self.with_extension(Restart)
+    }
+
+    /// Adds a widget's state to the bar, along with any template keys it draws.
+    pub(crate) fn with_extension(mut self, extension: impl BarExtension<V> + 'static) -> Self {
+        self.extensions.push(Box::new(extension));
+        self.restyle();
+        self
+    }
+
+    /// Redraws the bar every `interval` while it's active, e.g. to animate a spinner or count a
+    /// timer down when the model isn't ticked often.
+    pub fn with_steady_tick(mut self, interval: Duration) -> Self {
+        self.steady_tick = Some(interval);
+        self
+    }
+
     pub fn with_static_message(mut self, message: impl Into<String>) -> Self {
         self.static_message = Some(message.into());
         self
@@ -226,34 +437,134 @@ impl<V> ProgressBarBindable<V> {
         self
     }
 
+    /// Gives the bar a stable identifier, reported to any [`crate::ProgressSink`] instead of the
+    /// bar's position in the tree.
+    pub fn with_id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
     pub fn with_style(mut self, style: ProgressStyle) -> Self {
-        self.base_style = style;
+        self.style = Some(style);
+        self.restyle();
         self
     }
 
     pub fn with_finish_style(mut self, style: ProgressStyle) -> Self {
         self.finish_style = Some(style);
+        self.restyle();
+        self
+    }
+
+    /// Style used when the bar is `Failed`. Falls back to the finish style if not set.
+    pub fn with_failed_style(mut self, style: ProgressStyle) -> Self {
+        self.failed_style = Some(style);
+        self.restyle();
         self
     }
 
     pub fn with_tick_chars(mut self, chars: &str) -> Self {
-        self.base_style = self.base_style.tick_chars(chars);
+        self.tick_chars = Some(chars.to_string());
+        self.restyle();
+        self
+    }
 
-        if let Some(finish_style) = self.finish_style.take() {
-            self.finish_style = Some(finish_style.tick_chars(chars));
-        }
+    /// Indents the bar by `level` times the theme's indentation, on top of the indentation of any
+    /// nested groups it's in. Bars with their own style are only indented if their template
+    /// includes the `{indent}` key.
+    pub fn with_indent(mut self, level: usize) -> Self {
+        self.indent = level;
+        self.restyle();
         self
     }
 
+    pub fn widget(&self) -> Widget {
+        self.widget
+    }
+
+    pub(crate) fn set_theme(&mut self, theme: &Arc<Theme>) {
+        self.theme = theme.clone();
+        self.restyle();
+    }
+
+    fn restyle(&mut self) {
+        // The `{indent}` key is replaced below to include the tree's indentation
+        // Themes are validated when they're set on the tree, so can always be styled
+        let themed = |kind| self.theme.style(self.widget, kind, 0).ok().flatten();
+        let base = self
+            .style
+            .clone()
+            .or_else(|| themed(StyleKind::Active))
+            .unwrap_or_else(ProgressStyle::default_bar);
+        let pending = themed(StyleKind::Pending);
+        let finish = self
+            .finish_style
+            .clone()
+            .or_else(|| themed(StyleKind::Finished));
+        let failed = self
+            .failed_style
+            .clone()
+            .or_else(|| themed(StyleKind::Failed));
+
+        let indent = self.theme.indent.repeat(self.indent);
+        let finalize = |style: ProgressStyle| {
+            let style = match &self.tick_chars {
+                Some(chars) => style.tick_chars(chars),
+                None => style,
+            };
+            let line_prefix = self.line_prefix.clone();
+            let indent = indent.clone();
+            let style = self.extensions.iter().fold(style, |style, extension| {
+                extension.style(style, &self.theme)
+            });
+            style.with_key(
+                "indent",
+                move |_: &ProgressState, w: &mut dyn fmt::Write| {
+                    let _ = w.write_str(&line_prefix.read().unwrap());
+                    let _ = w.write_str(&indent);
+                },
+            )
+        };
+        self.resolved = ResolvedStyles {
+            base: finalize(base),
+            pending: pending.map(finalize),
+            finish: finish.map(finalize),
+            failed: failed.map(finalize),
+        };
+        self.columns.restyle(&self.resolved);
+    }
+
+    pub(crate) fn set_line_prefix(&self, prefix: String) {
+        *self.line_prefix.write().unwrap() = prefix;
+    }
+
+    pub(crate) fn set_column_widths(&self, widths: ColumnWidths) {
+        self.columns.set_widths(widths);
+    }
+
     // Used by the MultiProgressWrapper to insert the bar
-    pub(crate) fn reparent(&mut self, multiprogress: MultiProgressWrapper, index: usize) {
-        self.progress_bar = ProgressBarWrapper::MultiProgress(multiprogress, index);
+    pub(crate) fn reparent(&mut self, multiprogress: MultiProgressWrapper, path: BarPath) {
+        self.progress_bar = ProgressBarWrapper::MultiProgress(multiprogress, path);
     }
 
     /// This is used specifically in the circumstances where a parent container might be hidden, so we
     /// force this progress bar to hide itself.
     pub fn tick_with_display_override(&self, model: &V, can_display: bool) {
+        let progress_state = self.resolve(model, can_display);
+        self.render(&progress_state);
+    }
+
+    /// Evaluates the binding against the model, filling in the static message and prefix where the
+    /// binding doesn't provide them.
+    pub(crate) fn resolve(&self, model: &V, can_display: bool) -> DisplayState<ProgressBarState> {
         let progress_state = if can_display {
+            for extension in &self.extensions {
+                extension.record(model);
+            }
             self.updater
                 .as_ref()
                 .map(|updater| updater(model))
@@ -262,49 +573,146 @@ impl<V> ProgressBarBindable<V> {
             DisplayState::FinishedAndHidden
         };
 
+        self.history.lock().unwrap().info.is_static = self.updater.is_none();
+        self.resolve_state(progress_state)
+    }
+
+    /// Resolves a state worked out outside the bar, e.g. by a [`crate::table::Table`], as if it
+    /// had come from the bar's binding.
+    pub(crate) fn resolve_state(
+        &self,
+        progress_state: DisplayState<ProgressBarState>,
+    ) -> DisplayState<ProgressBarState> {
+        let state = match progress_state {
+            DisplayState::Active(progress) => DisplayState::Active(self.with_statics(progress)),
+            DisplayState::Finished(progress) => DisplayState::Finished(self.with_statics(progress)),
+            DisplayState::Failed(progress) => DisplayState::Failed(self.with_statics(progress)),
+            other => other,
+        };
+
+        let mut history = self.history.lock().unwrap();
+        if history.last.as_ref() != Some(&state) {
+            let now = Instant::now();
+            history.info.updated_at = Some(now);
+            history.info.finished_at = match state {
+                DisplayState::Finished(_) | DisplayState::Failed(_) => {
+                    history.info.finished_at.or(Some(now))
+                }
+                _ => None,
+            };
+            history.last = Some(state.clone());
+        }
+        state
+    }
+
+    /// When the state last resolved changed, and became finished or failed.
+    pub(crate) fn info(&self) -> BarInfo {
+        BarInfo {
+            drawn_pending: self.resolved.pending.is_some(),
+            ..self.history.lock().unwrap().info
+        }
+    }
+
+    fn with_statics(&self, mut progress: ProgressBarState) -> ProgressBarState {
+        if progress.message.is_none() {
+            progress.message = self.static_message.clone();
+        }
+        if progress.prefix.is_none() {
+            progress.prefix = self.static_prefix.clone();
+        }
+        progress
+    }
+
+    /// Pushes an already resolved state to the underlying progress bar, creating or removing it as
+    /// needed.
+    pub(crate) fn render(&self, progress_state: &DisplayState<ProgressBarState>) {
+        let drawn = self.draw(progress_state);
+        let (progress_bar, finish_style) = match &drawn {
+            Some((progress_bar, finish_style)) => (Some(progress_bar), finish_style.as_ref()),
+            None => (None, None),
+        };
+        for extension in &self.extensions {
+            extension.drawn(progress_bar, progress_state, finish_style);
+        }
+    }
+
+    // Draws the state, returning the bar it's drawn with and the style it's given when it finishes
+    fn draw(
+        &self,
+        progress_state: &DisplayState<ProgressBarState>,
+    ) -> Option<(ProgressBar, Option<ProgressStyle>)> {
         let already_created = self.progress_bar.is_created();
-        match &progress_state {
+        // Bars with a pending style are drawn before they start, with only their static text
+        let pending_progress;
+        let progress = match progress_state {
+            DisplayState::Active(progress)
+            | DisplayState::Finished(progress)
+            | DisplayState::Failed(progress) => progress,
+            DisplayState::NotStarted if self.resolved.pending.is_some() => {
+                pending_progress = self.with_statics(ProgressBarState::default());
+                &pending_progress
+            }
             DisplayState::NotStarted | DisplayState::FinishedAndHidden => {
                 if already_created {
                     self.progress_bar.remove();
                 }
+                return None;
             }
-            DisplayState::Active(progress) | DisplayState::Finished(progress) => {
-                if let Some(progress_bar) = self.progress_bar.get_or_create() {
-                    if !already_created {
-                        progress_bar.set_style(self.base_style.clone());
-                    }
+        };
+        let progress_bar = self.progress_bar.get_or_create()?;
 
-                    if let Some(msg) = progress.message.as_ref().or(self.static_message.as_ref()) {
-                        progress_bar.set_message(msg.clone());
-                    } else {
-                        progress_bar.set_message("".to_string());
-                    }
+        // Any extension may reset the bar, e.g. a finished checklist step that's retried
+        let mut restarted = false;
+        for extension in &self.extensions {
+            restarted |= extension.drawing(&progress_bar, progress_state);
+        }
+        let widths = self.columns.widths();
+        let applied = AppliedStyle {
+            pending: matches!(progress_state, DisplayState::NotStarted),
+            aligned: widths.pads_position(),
+        };
+        let restyled = self.applied.lock().unwrap().replace(applied) != Some(applied);
+        let styles = if applied.aligned {
+            self.columns.styles()
+        } else {
+            &self.resolved
+        };
+        if !already_created || restarted || restyled {
+            match (&styles.pending, applied.pending) {
+                (Some(pending), true) => progress_bar.set_style(pending.clone()),
+                _ => progress_bar.set_style(styles.base.clone()),
+            }
+            match (self.steady_tick, progress_state) {
+                (Some(interval), DisplayState::Active(_)) => {
+                    progress_bar.enable_steady_tick(interval)
+                }
+                _ => progress_bar.disable_steady_tick(),
+            }
+        }
 
-                    if let Some(prefix) = progress.prefix.as_ref().or(self.static_prefix.as_ref()) {
-                        progress_bar.set_prefix(prefix.clone());
-                    } else {
-                        progress_bar.set_prefix("".to_string());
-                    }
+        progress_bar.set_message(pad(progress.message(), widths.message));
+        progress_bar.set_prefix(pad(progress.prefix(), widths.prefix));
 
-                    if let Some((position, length)) = &progress.position_and_len {
-                        progress_bar.set_length(*length);
-                        progress_bar.set_position(*position);
-                    } else {
-                        progress_bar.unset_length();
-                        progress_bar.set_position(0);
-                    }
+        if let Some((position, length)) = &progress.position_and_len {
+            progress_bar.set_length(*length);
+            progress_bar.set_position(*position);
+        } else {
+            progress_bar.unset_length();
+            progress_bar.set_position(0);
+        }
 
-                    progress_bar.tick();
-                    if progress_state.is_finished() {
-                        if let Some(finish_style) = &self.finish_style {
-                            progress_bar.set_style(finish_style.clone());
-                        }
-                        progress_bar.finish();
-                    }
-                }
+        progress_bar.tick();
+        let finish_style = match progress_state {
+            DisplayState::Failed(_) => styles.failed.as_ref().or(styles.finish.as_ref()),
+            _ => styles.finish.as_ref(),
+        };
+        if progress_state.is_finished() {
+            if let Some(finish_style) = finish_style {
+                progress_bar.set_style(finish_style.clone());
             }
+            progress_bar.finish();
         }
+        Some((progress_bar, finish_style.cloned()))
     }
 
     pub fn tick(&self, model: &V) {
@@ -312,10 +720,17 @@ impl<V> ProgressBarBindable<V> {
     }
 }
 
+// Pads `text` with spaces to `width` columns
+fn pad(text: Option<&str>, width: usize) -> String {
+    let text = text.unwrap_or_default();
+    let padding = width.saturating_sub(console::measure_text_width(text));
+    format!("{text}{}", " ".repeat(padding))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::helpers::standalone::*;
+    use crate::helpers::widgets;
 
     #[derive(Default, Debug)]
     enum TestState {
@@ -324,11 +739,13 @@ mod tests {
         Started,
         Finished,
     }
-    impl TestState {
-        fn to_string(&self) -> String {
-            format!("{:?}", self)
+    impl std::fmt::Display for TestState {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self)
         }
+    }
 
+    impl TestState {
         fn next(&mut self) {
             *self = match self {
                 TestState::NotStarted => TestState::Started,
@@ -380,11 +797,11 @@ mod tests {
     #[test]
     fn test_spacer() {
         let mut vm = TestViewModel::default();
-        let spacer = spacer::<TestViewModel>();
+        let spacer = widgets::spacer::<TestViewModel>().with_target(BindingTarget::Standalone);
 
         for _i in 0..3 {
             spacer.tick(&vm);
-            assert_eq!(spacer.static_message, Some("".to_string()));
+            assert_eq!(spacer.static_message, Some(" ".to_string()));
             assert!(spacer.progress_bar.is_finished());
             vm.state.next();
         }
@@ -393,7 +810,8 @@ mod tests {
     #[test]
     fn test_message_static() {
         let mut vm = TestViewModel::default();
-        let message = message_static::<TestViewModel>("Static Message".to_string());
+        let message = widgets::message_static::<TestViewModel>("Static Message".to_string())
+            .with_target(BindingTarget::Standalone);
 
         for _i in 0..3 {
             message.tick(&vm);
@@ -406,18 +824,19 @@ mod tests {
     #[test]
     fn test_bind_display_state() {
         let mut vm = TestViewModel::default();
-        let message = message_static::<TestViewModel>("Static Message".to_string())
+        let message = widgets::message_static::<TestViewModel>("Static Message".to_string())
+            .with_target(BindingTarget::Standalone)
             .bind_display_state(TestViewModel::get_display_state);
 
         vm.state = TestState::Started;
         message.tick(&vm);
         assert_eq!(message.static_message, Some("Static Message".to_string()));
-        assert_eq!(message.progress_bar.is_finished(), false);
+        assert!(!message.progress_bar.is_finished());
 
         vm.state = TestState::Finished;
         message.tick(&vm);
         assert_eq!(message.static_message, Some("Static Message".to_string()));
-        assert_eq!(message.progress_bar.is_finished(), true);
+        assert!(message.progress_bar.is_finished());
         // TODO: Work how how to check if the bar is cleared
     }
 
@@ -480,7 +899,8 @@ mod tests {
     fn test_progress() {
         let mut vm = TestViewModel::default();
 
-        let progress = progress_bar_default(TestViewModel::get_progress);
+        let progress = widgets::progress_bar_default(TestViewModel::get_progress)
+            .with_target(BindingTarget::Standalone);
 
         let inner_bar = || progress.progress_bar.get_inner_progress_bar().unwrap();
 
@@ -520,4 +940,63 @@ mod tests {
         );
         assert_eq!(inner_bar().prefix(), "", "Final prefix should be empty");
     }
+
+    #[test]
+    fn test_binding_target() {
+        let vm = TestViewModel {
+            state: TestState::Started,
+        };
+
+        let unbound = widgets::message(TestViewModel::get_message);
+        unbound.tick(&vm);
+        assert!(unbound.progress_bar.get_inner_progress_bar().is_none());
+
+        let standalone = unbound.with_target(BindingTarget::Standalone);
+        standalone.tick(&vm);
+        assert_eq!(
+            standalone
+                .progress_bar
+                .get_inner_progress_bar()
+                .unwrap()
+                .message(),
+            "Started"
+        );
+
+        // The same widgets behave the same way in a tree
+        let term = InMemoryTerm::new(10, 40);
+        let tree = crate::ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                widgets::message(TestViewModel::get_message).into(),
+                widgets::spacer().into(),
+                standalone.into(),
+            ],
+        );
+        tree.tick(&vm);
+        assert_eq!(term.contents(), "Started\n\nStarted");
+    }
+
+    #[test]
+    fn test_restartable() {
+        let bar = || {
+            ProgressBarBindable::themed(Widget::Spinner)
+                .bind_message(|active: &bool| {
+                    if *active {
+                        DisplayState::Active("waiting".to_string())
+                    } else {
+                        DisplayState::Finished("waiting".to_string())
+                    }
+                })
+                .with_target(BindingTarget::Standalone)
+        };
+        let (fixed, restartable) = (bar(), bar().restartable());
+        for bar in [&fixed, &restartable] {
+            bar.tick(&true);
+            bar.tick(&false);
+            bar.tick(&true);
+        }
+        // Only a restartable bar is reset when it's active again, the other stays finished
+        assert!(fixed.progress_bar.is_finished());
+        assert!(!restartable.progress_bar.is_finished());
+    }
 }
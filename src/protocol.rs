@@ -0,0 +1,98 @@
+//! Reads task progress from another process.
+//!
+//! The protocol is one JSON object per line, each describing a change to a single task:
+//!
+//! ```json
+//! {"id": "compile", "state": "active", "message": "Compiling", "len": 120}
+//! {"id": "compile/foo.rs", "parent": "compile", "state": "active"}
+//! {"id": "compile", "pos": 12}
+//! {"id": "compile/foo.rs", "state": "finished"}
+//! ```
+//!
+//! | Field     | Type   | Meaning                                                          |
+//! |-----------|--------|------------------------------------------------------------------|
+//! | `id`      | string | Required. Identifies the task in later lines.                    |
+//! | `parent`  | string | Id of the task this one is nested under.                         |
+//! | `state`   | string | `pending`, `active`, `finished`, `failed` or `hidden`.           |
+//! | `message` | string | Text shown for the task. Defaults to the id.                     |
+//! | `pos`     | number | Current position. Shown as a progress bar when `len` is also set. |
+//! | `len`     | number | Total length.                                                    |
+//!
+//! Any field other than `id` can be left out, in which case it keeps its previous value. Lines
+//! that aren't valid updates are passed through untouched, so a tool can mix progress and
+//! ordinary output.
+//!
+//! Updates are applied to a [`TaskModel`], which can be rendered with [`crate::tasks::task_tree`].
+
+use crate::tasks::{TaskModel, TaskUpdate};
+use std::{
+    io::{self, BufRead},
+    sync::Arc,
+    thread::{self, JoinHandle},
+};
+
+/// Parses a single line of the protocol.
+pub fn parse_task_update(line: &str) -> Option<TaskUpdate> {
+    serde_json::from_str(line).ok()
+}
+
+/// Applies every update read from `reader` to `model` until the reader is exhausted. Lines that
+/// aren't updates are passed to `on_other_line`.
+pub fn read_task_updates(
+    reader: impl BufRead,
+    model: &TaskModel,
+    mut on_other_line: impl FnMut(&str),
+) -> io::Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        match parse_task_update(line.trim()) {
+            Some(update) => model.apply(update),
+            None if line.trim().is_empty() => {}
+            None => on_other_line(&line),
+        }
+    }
+    Ok(())
+}
+
+/// Reads updates on a background thread, discarding any other output.
+pub fn spawn_task_reader(
+    reader: impl BufRead + Send + 'static,
+    model: Arc<TaskModel>,
+) -> JoinHandle<io::Result<()>> {
+    thread::spawn(move || read_task_updates(reader, &model, |_| {}))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tasks::TaskState;
+
+    #[test]
+    fn test_read_task_updates() {
+        let input = concat!(
+            "{\"id\": \"compile\", \"state\": \"active\", \"message\": \"Compiling\", \"len\": 4}\n",
+            "warning: unused variable\n",
+            "\n",
+            "{\"id\": \"compile/foo.rs\", \"parent\": \"compile\", \"state\": \"failed\"}\n",
+            "{\"id\": \"compile\", \"pos\": 2}\n",
+        );
+
+        let model = TaskModel::new();
+        let mut other_lines = Vec::new();
+        read_task_updates(input.as_bytes(), &model, |line| {
+            other_lines.push(line.to_string())
+        })
+        .unwrap();
+
+        assert_eq!(other_lines, vec!["warning: unused variable"]);
+
+        let compile = model.task("compile").unwrap();
+        assert_eq!(compile.state, TaskState::Active);
+        assert_eq!(compile.label(), "Compiling");
+        assert_eq!((compile.position, compile.length), (Some(2), Some(4)));
+
+        let file = model.task("compile/foo.rs").unwrap();
+        assert_eq!(file.parent.as_deref(), Some("compile"));
+        assert_eq!(file.state, TaskState::Failed);
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{
+    DisplayState, ProgressBarBindable, ProgressBarState, progress_bar_bindable::BarInfo,
+    theme::Theme,
+};
+use std::{ops::Range, sync::Arc, time::Instant};
+
+/// The state of a container for one tick. Trees resolve every bar before drawing any of them, so
+/// how a bar is laid out can depend on which of its siblings are visible.
+pub(crate) enum Resolved {
+    Leaf(DisplayState<ProgressBarState>, BarInfo),
+    Group(Box<ResolvedGroup>),
+}
+
+pub(crate) struct ResolvedGroup {
+    pub(crate) children: Vec<Resolved>,
+    // Where each child sits in the tree, as the last index of its path
+    pub(crate) slots: Vec<usize>,
+    // The items (see `items`) in the order they're drawn, if not declaration order
+    pub(crate) order: Option<Vec<usize>>,
+    pub(crate) header: Option<DisplayState<ProgressBarState>>,
+    // Only shown while collapsed
+    pub(crate) summary: Option<DisplayState<ProgressBarState>>,
+    // The line standing in for the items left out by `max_visible`
+    pub(crate) overflow: Option<DisplayState<ProgressBarState>>,
+    // The children are hidden
+    pub(crate) collapsed: bool,
+    // A nested group without a header or summary, drawn as part of the sibling above it
+    pub(crate) continues_previous: bool,
+    // Which items (see `items`) are drawn, if not all of them
+    pub(crate) shown: Option<Vec<bool>>,
+}
+
+impl Resolved {
+    pub(crate) fn is_visible(&self) -> bool {
+        match self {
+            Resolved::Leaf(state, info) => is_drawn(state, info),
+            Resolved::Group(group) => {
+                [&group.header, &group.summary, &group.overflow]
+                    .into_iter()
+                    .flatten()
+                    .any(is_visible)
+                    || (!group.collapsed
+                        && group.items().into_iter().enumerate().any(|(index, item)| {
+                            group.is_shown(index)
+                                && group.children[item].iter().any(Resolved::is_visible)
+                        }))
+            }
+        }
+    }
+
+    /// When the most recently finished bar in the container finished.
+    pub(crate) fn finished_at(&self) -> Option<Instant> {
+        self.latest(&|info| info.finished_at)
+    }
+
+    /// When the most recently updated bar in the container was updated.
+    pub(crate) fn updated_at(&self) -> Option<Instant> {
+        self.latest(&|info| info.updated_at)
+    }
+
+    fn latest(&self, time: &dyn Fn(&BarInfo) -> Option<Instant>) -> Option<Instant> {
+        match self {
+            Resolved::Leaf(_, info) => time(info),
+            Resolved::Group(group) => group
+                .children
+                .iter()
+                .filter_map(|child| child.latest(time))
+                .max(),
+        }
+    }
+}
+
+impl ResolvedGroup {
+    /// The ranges of children drawn as one item: a child, followed by any nested groups that
+    /// continue it.
+    pub(crate) fn items(&self) -> Vec<Range<usize>> {
+        let mut items: Vec<Range<usize>> = Vec::new();
+        for (index, child) in self.children.iter().enumerate() {
+            let continues_previous =
+                matches!(child, Resolved::Group(group) if group.continues_previous);
+            match items.last_mut() {
+                Some(item) if continues_previous => item.end = index + 1,
+                _ => items.push(index..index + 1),
+            }
+        }
+        items
+    }
+
+    /// The items with their indices, in the order they're drawn.
+    pub(crate) fn ordered_items(&self) -> Vec<(usize, Range<usize>)> {
+        let items = self.items();
+        match &self.order {
+            Some(order) => order
+                .iter()
+                .map(|item| (*item, items[*item].clone()))
+                .collect(),
+            None => items.into_iter().enumerate().collect(),
+        }
+    }
+
+    pub(crate) fn is_shown(&self, item: usize) -> bool {
+        self.shown.as_ref().is_none_or(|shown| shown[item])
+    }
+}
+
+pub(crate) fn is_visible(state: &DisplayState<ProgressBarState>) -> bool {
+    matches!(
+        state,
+        DisplayState::Active(_) | DisplayState::Finished(_) | DisplayState::Failed(_)
+    )
+}
+
+/// Whether a bar draws a line in `state`, which some bars also do before they start.
+pub(crate) fn is_drawn(state: &DisplayState<ProgressBarState>, info: &BarInfo) -> bool {
+    is_visible(state) || (info.drawn_pending && matches!(state, DisplayState::NotStarted))
+}
+
+/// Draws a resolved leaf, indented for its place in the tree.
+pub(crate) fn render_leaf<M>(
+    bar: &ProgressBarBindable<M>,
+    state: &DisplayState<ProgressBarState>,
+    context: &mut RenderContext,
+) {
+    if is_drawn(state, &bar.info()) {
+        bar.set_line_prefix(context.next_line_prefix());
+    }
+    bar.render(state);
+}
+
+/// Tracks the nested groups above the bar being drawn, to work out its indentation.
+pub(crate) struct RenderContext {
+    theme: Arc<Theme>,
+    guides: bool,
+    levels: Vec<Level>,
+}
+
+struct Level {
+    // Whether this is the last visible child of its group
+    last: bool,
+    // Whether no line directly in this child has been drawn yet
+    first_line: bool,
+}
+
+impl Default for RenderContext {
+    fn default() -> Self {
+        Self::new(Arc::new(Theme::default()), false)
+    }
+}
+
+impl RenderContext {
+    pub(crate) fn new(theme: Arc<Theme>, guides: bool) -> Self {
+        Self {
+            theme,
+            guides,
+            levels: Vec::new(),
+        }
+    }
+
+    /// Runs `f` for a child of a nested group, one level deeper than the group.
+    pub(crate) fn nested<R>(&mut self, last: bool, f: impl FnOnce(&mut Self) -> R) -> R {
+        self.levels.push(Level {
+            last,
+            first_line: true,
+        });
+        let result = f(self);
+        self.levels.pop();
+        result
+    }
+
+    /// The indentation for the next visible line. The first line directly in a child of a nested
+    /// group gets a branch, any other line continues the guides of the levels above it.
+    pub(crate) fn next_line_prefix(&mut self) -> String {
+        let mut prefix = String::new();
+        let depth = self.levels.len();
+        for (index, level) in self.levels.iter_mut().enumerate() {
+            let first_line = index + 1 == depth && level.first_line;
+            let part = if !self.guides {
+                &self.theme.indent
+            } else {
+                match (first_line, level.last) {
+                    (true, false) => &self.theme.guide_branch,
+                    (true, true) => &self.theme.guide_last,
+                    (false, false) => &self.theme.guide_continue,
+                    (false, true) => &self.theme.guide_blank,
+                }
+            };
+            prefix.push_str(part);
+        }
+        if let Some(level) = self.levels.last_mut() {
+            level.first_line = false;
+        }
+        prefix
+    }
+}
+
+/// Draws a bar `width` wide like indicatif's `{bar}`: the first of `chars` fills the done part,
+/// the last fills the rest, and any between show how full the cell at the boundary is.
+pub(crate) fn draw_bar(fraction: f64, width: usize, chars: &str) -> String {
+    let chars: Vec<char> = chars.chars().collect();
+    let (Some(full), Some(empty)) = (chars.first(), chars.last()) else {
+        return " ".repeat(width);
+    };
+    let partial = chars
+        .get(1..chars.len().saturating_sub(1))
+        .unwrap_or_default();
+
+    let fill = fraction.clamp(0.0, 1.0) * width as f64;
+    let filled = fill as usize;
+    let mut bar: String = std::iter::repeat_n(*full, filled).collect();
+    if filled < width {
+        if partial.is_empty() {
+            bar.push(*empty);
+        } else {
+            let level = (fill.fract() * partial.len() as f64) as usize;
+            bar.push(partial[partial.len() - 1 - level]);
+        }
+        bar.extend(std::iter::repeat_n(*empty, width - filled - 1));
+    }
+    bar
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{DisplayState, ProgressBarTree, TerminalCapabilities, helpers::widgets::*, tree};
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    struct Model {
+        show_last: bool,
+    }
+
+    fn last(model: &Model) -> DisplayState<String> {
+        if model.show_last {
+            DisplayState::Active("c".to_string())
+        } else {
+            DisplayState::NotStarted
+        }
+    }
+
+    fn build(term: &InMemoryTerm) -> ProgressBarTree<Model> {
+        ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            tree! {
+                message_static("root"),
+                nested {
+                    message_static("a"),
+                    nested {
+                        message_static("a1"),
+                        message_static("a2"),
+                    },
+                    message_static("b"),
+                    nested {
+                        message_static("b1"),
+                    },
+                    message(last),
+                },
+            },
+        )
+        .with_capabilities(TerminalCapabilities::full())
+    }
+
+    #[test]
+    fn test_guides_follow_visibility() {
+        let term = InMemoryTerm::new(10, 40);
+        let tree = build(&term).with_guides(true);
+
+        tree.tick(&Model { show_last: true });
+        assert_eq!(
+            term.contents(),
+            "root\n├─ a\n│  ├─ a1\n│  └─ a2\n├─ b\n│  └─ b1\n└─ c"
+        );
+
+        // `b` becomes the last child, so its guides close
+        tree.tick(&Model { show_last: false });
+        assert_eq!(
+            term.contents(),
+            "root\n├─ a\n│  ├─ a1\n│  └─ a2\n└─ b\n   └─ b1"
+        );
+    }
+
+    #[test]
+    fn test_nesting_without_guides_indents() {
+        let term = InMemoryTerm::new(10, 40);
+        let tree = build(&term);
+
+        tree.tick(&Model { show_last: true });
+        assert_eq!(
+            term.contents(),
+            "root\n  a\n    a1\n    a2\n  b\n    b1\n  c"
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use crate::{DisplayState, ProgressBarState};
+
+/// The resolved state of a single leaf, as seen by a [`ProgressSink`] during a tick.
+pub struct LeafUpdate<'a> {
+    /// The id set with [`crate::ProgressBarBindable::with_id`], if any.
+    pub id: Option<&'a str>,
+    /// The position of the leaf in the tree, as child indices from the root.
+    pub path: &'a [usize],
+    pub state: &'a DisplayState<ProgressBarState>,
+}
+
+impl LeafUpdate<'_> {
+    /// The explicit id if one was set, otherwise the path joined with `.` (e.g. `"0.3.1"`).
+    pub fn stable_id(&self) -> String {
+        match self.id {
+            Some(id) => id.to_string(),
+            None => self
+                .path
+                .iter()
+                .map(|index| index.to_string())
+                .collect::<Vec<_>>()
+                .join("."),
+        }
+    }
+}
+
+/// Receives the state of every leaf each time a [`crate::ProgressBarTree`] is ticked, alongside
+/// (or instead of, with a hidden draw target) the terminal rendering.
+pub trait ProgressSink: Send {
+    fn leaf_ticked(&mut self, leaf: &LeafUpdate<'_>);
+
+    /// Called once all leaves have been visited for the current tick.
+    fn tick_finished(&mut self) {}
+}
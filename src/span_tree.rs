@@ -0,0 +1,339 @@
+use crate::tasks::{TaskModel, TaskState, TaskUpdate};
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt::{self, Write},
+    sync::{Arc, Mutex},
+};
+use tracing_core::{
+    Subscriber,
+    field::{Field, Visit},
+    span::{Attributes, Id, Record},
+};
+use tracing_subscriber::{Layer, layer::Context};
+
+/// What happens to a span's row once the span closes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SpanClose {
+    /// Remove the span from the tree.
+    #[default]
+    Remove,
+    /// Keep the span as a finished row showing how long it was open. Only the last `keep` closed
+    /// spans under each parent are kept, and a span's closed children are removed when it closes,
+    /// leaving its own row to show how long they took together.
+    Collapse { keep: usize },
+}
+
+/// A `tracing` [`Layer`] that mirrors open spans into a [`TaskModel`], so they can be rendered
+/// as a live, nested tree with [`crate::tasks::task_tree`].
+///
+/// Each span is shown with its name and fields. Spans with `pos` and `len` fields are shown as
+/// progress bars, and can be advanced by recording new values:
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use declaratif::{SpanTreeLayer, tasks::TaskModel};
+/// # use tracing_subscriber::layer::SubscriberExt;
+/// let model = Arc::new(TaskModel::new());
+/// let subscriber = tracing_subscriber::registry().with(SpanTreeLayer::new(model.clone()));
+///
+/// tracing::subscriber::with_default(subscriber, || {
+///     let span = tracing::info_span!("download", pos = 0, len = 100);
+///     span.record("pos", 50);
+/// });
+/// ```
+pub struct SpanTreeLayer {
+    model: Arc<TaskModel>,
+    on_close: SpanClose,
+    spans: Mutex<SpanTable>,
+}
+
+#[derive(Default)]
+struct SpanTable {
+    // Span ids are reused once closed, so each span gets its own task id
+    next_task_id: u64,
+    open: HashMap<Id, OpenSpan>,
+    // Task ids of the closed spans still shown, oldest first, by the task id of their parent
+    collapsed: HashMap<Option<String>, VecDeque<String>>,
+}
+
+struct OpenSpan {
+    task_id: String,
+    parent: Option<String>,
+    name: &'static str,
+    fields: SpanFields,
+}
+
+#[derive(Default)]
+struct SpanFields {
+    values: Vec<(&'static str, String)>,
+    pos: Option<u64>,
+    len: Option<u64>,
+}
+
+impl SpanFields {
+    fn set(&mut self, field: &Field, value: String) {
+        match self
+            .values
+            .iter_mut()
+            .find(|(name, _)| *name == field.name())
+        {
+            Some((_, existing)) => *existing = value,
+            None => self.values.push((field.name(), value)),
+        }
+    }
+
+    fn set_number(&mut self, field: &Field, value: u64) -> bool {
+        match field.name() {
+            "pos" => self.pos = Some(value),
+            "len" => self.len = Some(value),
+            _ => return false,
+        }
+        true
+    }
+
+    fn message(&self, name: &str) -> String {
+        let mut message = name.to_string();
+        for (field, value) in self.values.iter() {
+            let _ = write!(message, " {field}={value}");
+        }
+        message
+    }
+}
+
+impl Visit for SpanFields {
+    fn record_u64(&mut self, field: &Field, value: u64) {
+        if !self.set_number(field, value) {
+            self.set(field, value.to_string());
+        }
+    }
+
+    fn record_i64(&mut self, field: &Field, value: i64) {
+        if value < 0 || !self.set_number(field, value as u64) {
+            self.set(field, value.to_string());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.set(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.set(field, format!("{value:?}"));
+    }
+}
+
+impl SpanTreeLayer {
+    pub fn new(model: Arc<TaskModel>) -> Self {
+        Self {
+            model,
+            on_close: SpanClose::default(),
+            spans: Mutex::new(SpanTable::default()),
+        }
+    }
+
+    pub fn on_close(mut self, on_close: SpanClose) -> Self {
+        self.on_close = on_close;
+        self
+    }
+
+    fn update(span: &OpenSpan) -> TaskUpdate {
+        TaskUpdate {
+            id: span.task_id.clone(),
+            message: Some(span.fields.message(span.name)),
+            pos: span.fields.pos,
+            len: span.fields.len,
+            ..Default::default()
+        }
+    }
+}
+
+impl<S: Subscriber> Layer<S> for SpanTreeLayer {
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = SpanFields::default();
+        attrs.record(&mut fields);
+
+        let parent = if attrs.is_contextual() {
+            ctx.current_span().id().cloned()
+        } else {
+            attrs.parent().cloned()
+        };
+
+        let mut spans = self.spans.lock().unwrap();
+        let parent = parent
+            .and_then(|parent| spans.open.get(&parent))
+            .map(|parent| parent.task_id.clone());
+
+        spans.next_task_id += 1;
+        let span = OpenSpan {
+            task_id: format!("span-{}", spans.next_task_id),
+            parent,
+            name: attrs.metadata().name(),
+            fields,
+        };
+
+        self.model.apply(TaskUpdate {
+            parent: span.parent.clone(),
+            state: Some(TaskState::Active),
+            ..Self::update(&span)
+        });
+        spans.open.insert(id.clone(), span);
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+        let mut spans = self.spans.lock().unwrap();
+        if let Some(span) = spans.open.get_mut(id) {
+            values.record(&mut span.fields);
+            self.model.apply(Self::update(span));
+        }
+    }
+
+    fn on_close(&self, id: Id, _ctx: Context<'_, S>) {
+        let mut spans = self.spans.lock().unwrap();
+        let Some(span) = spans.open.remove(&id) else {
+            return;
+        };
+
+        match self.on_close {
+            SpanClose::Remove => self.model.remove(&span.task_id),
+            SpanClose::Collapse { keep } => {
+                self.model.apply(TaskUpdate {
+                    id: span.task_id.clone(),
+                    state: Some(TaskState::Finished),
+                    ..Default::default()
+                });
+                if let Some(elapsed) = self
+                    .model
+                    .task(&span.task_id)
+                    .and_then(|task| task.elapsed())
+                {
+                    self.model.apply(TaskUpdate {
+                        id: span.task_id.clone(),
+                        message: Some(format!(
+                            "{} ({:.2}s)",
+                            span.fields.message(span.name),
+                            elapsed.as_secs_f32()
+                        )),
+                        ..Default::default()
+                    });
+                }
+
+                // The span's own row now covers its children, and only the newest rows are kept
+                let children = spans.collapsed.remove(&Some(span.task_id.clone()));
+                for child in children.unwrap_or_default() {
+                    self.model.remove(&child);
+                }
+                let siblings = spans.collapsed.entry(span.parent).or_default();
+                siblings.push_back(span.task_id);
+                while siblings.len() > keep {
+                    if let Some(oldest) = siblings.pop_front() {
+                        self.model.remove(&oldest);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::layer::SubscriberExt;
+
+    fn labels(model: &TaskModel, parent: Option<&str>) -> Vec<String> {
+        model
+            .children(parent)
+            .iter()
+            .map(|id| model.task(id).unwrap().label().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_spans_become_nested_tasks() {
+        let model = Arc::new(TaskModel::new());
+        let subscriber = tracing_subscriber::registry()
+            .with(SpanTreeLayer::new(model.clone()).on_close(SpanClose::Collapse { keep: 4 }));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let build = tracing::info_span!("build", krate = "declaratif");
+            let _entered = build.enter();
+            assert_eq!(labels(&model, None), vec!["build krate=declaratif"]);
+
+            let compile =
+                tracing::info_span!("compile", pos = 0, len = 10, file = tracing::field::Empty);
+            compile.record("pos", 4);
+            compile.record("file", "lib.rs");
+
+            let top = model.children(None).remove(0);
+            let nested = model.children(Some(&top));
+            assert_eq!(labels(&model, Some(&top)), vec!["compile file=lib.rs"]);
+            let task = model.task(&nested[0]).unwrap();
+            assert_eq!((task.position, task.length), (Some(4), Some(10)));
+            assert_eq!(task.state, TaskState::Active);
+
+            drop(compile);
+            let task = model.task(&nested[0]).unwrap();
+            assert_eq!(task.state, TaskState::Finished);
+            assert!(task.label().starts_with("compile file=lib.rs ("));
+        });
+    }
+
+    #[test]
+    fn test_closed_spans_can_be_removed() {
+        let model = Arc::new(TaskModel::new());
+        let subscriber = tracing_subscriber::registry()
+            .with(SpanTreeLayer::new(model.clone()).on_close(SpanClose::Remove));
+
+        tracing::subscriber::with_default(subscriber, || {
+            let outer = tracing::info_span!("outer");
+            let inner = tracing::info_span!(parent: &outer, "inner");
+            let outer_id = model.children(None).remove(0);
+            assert_eq!(labels(&model, Some(&outer_id)), vec!["inner"]);
+
+            drop(inner);
+            assert!(model.children(Some(&outer_id)).is_empty());
+            assert_eq!(labels(&model, None), vec!["outer"]);
+
+            drop(outer);
+            assert!(model.children(None).is_empty());
+        });
+    }
+
+    #[test]
+    fn test_closed_spans_stay_bounded() {
+        let model = Arc::new(TaskModel::new());
+        let subscriber = tracing_subscriber::registry()
+            .with(SpanTreeLayer::new(model.clone()).on_close(SpanClose::Collapse { keep: 2 }));
+
+        tracing::subscriber::with_default(subscriber, || {
+            for index in 0..100 {
+                let outer = tracing::info_span!("outer", index);
+                for _ in 0..10 {
+                    drop(tracing::info_span!(parent: &outer, "inner"));
+                }
+                let current = model.children(None).pop().unwrap();
+                assert_eq!(model.children(Some(&current)).len(), 2);
+                drop(outer);
+            }
+
+            // Only the newest outer spans are kept, without their inner spans
+            let kept = labels(&model, None);
+            assert_eq!(kept.len(), 2);
+            assert!(kept[0].starts_with("outer index=98 ("));
+            assert!(kept[1].starts_with("outer index=99 ("));
+            for outer in model.children(None) {
+                assert!(model.children(Some(&outer)).is_empty());
+            }
+        });
+
+        // Closed spans are removed by default
+        let model = Arc::new(TaskModel::new());
+        let subscriber = tracing_subscriber::registry().with(SpanTreeLayer::new(model.clone()));
+        tracing::subscriber::with_default(subscriber, || {
+            for _ in 0..100 {
+                let outer = tracing::info_span!("outer");
+                drop(tracing::info_span!(parent: &outer, "inner"));
+            }
+        });
+        assert!(model.children(None).is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+use crate::{
+    DisplayState, ProgressBarBindable, ProgressBarTreeContainer,
+    extension::BarExtension,
+    helpers::tree::{group, nested},
+    summary::format_duration,
+    theme::{Theme, Widget},
+};
+use console::Style;
+use indicatif::{ProgressState, ProgressStyle};
+use std::{
+    fmt,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// Where a [`Step`] of a checklist has got to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+    Skipped,
+}
+
+type StatusSource<V> = Arc<dyn Fn(&V) -> StepStatus + Send + Sync>;
+
+/// One line of a [`crate::helpers::tree::checklist`], with the time it took once it has run.
+pub struct Step<V: Send + Sync> {
+    name: String,
+    status: StatusSource<V>,
+    details: Option<ProgressBarTreeContainer<V>>,
+}
+
+impl<V: Send + Sync + 'static> Step<V> {
+    pub fn new(
+        name: impl Into<String>,
+        status: impl Fn(&V) -> StepStatus + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            status: Arc::new(status),
+            details: None,
+        }
+    }
+
+    /// Shows `details` nested under the step while it's running, e.g. the bars of each file being
+    /// downloaded.
+    pub fn with_details(mut self, details: impl Into<ProgressBarTreeContainer<V>>) -> Self {
+        self.details = Some(details.into());
+        self
+    }
+
+    pub(crate) fn build(self) -> ProgressBarTreeContainer<V> {
+        let Step {
+            name,
+            status,
+            details,
+        } = self;
+        let timing = Mutex::new(StepTiming::default());
+
+        let progress = {
+            let glyph = StepGlyph {
+                status: status.clone(),
+                skipped: Arc::new(AtomicBool::new(false)),
+            };
+            let status = status.clone();
+            ProgressBarBindable::themed(Widget::Step)
+                .with_static_message(name.clone())
+                .restartable()
+                .with_extension(glyph)
+                .bind_message(move |model| {
+                    let status = status(model);
+                    let elapsed = timing.lock().unwrap().update(status);
+                    let message = match elapsed {
+                        Some(elapsed) => format!("{name} ({})", format_duration(elapsed)),
+                        None => name.clone(),
+                    };
+                    match status {
+                        StepStatus::Pending => DisplayState::NotStarted,
+                        StepStatus::Running => DisplayState::Active(message),
+                        StepStatus::Done | StepStatus::Skipped => DisplayState::Finished(message),
+                        StepStatus::Failed => DisplayState::Failed(message),
+                    }
+                })
+        };
+
+        let mut children = vec![progress.into()];
+        if let Some(details) = details {
+            children.push(
+                nested(vec![details]).with_display_condition(Box::new(move |model| {
+                    status(model) == StepStatus::Running
+                })),
+            );
+        }
+        group(children)
+    }
+}
+
+#[derive(Default)]
+struct StepTiming {
+    started: Option<Instant>,
+    took: Option<Duration>,
+}
+
+impl StepTiming {
+    /// Starts or stops the clock for `status`, returning how long the step has been running.
+    fn update(&mut self, status: StepStatus) -> Option<Duration> {
+        match status {
+            StepStatus::Running => {
+                // A step that's run again, e.g. retried after failing, is timed from its new start
+                if self.took.take().is_some() || self.started.is_none() {
+                    self.started = Some(Instant::now());
+                }
+                self.started.map(|started| started.elapsed())
+            }
+            StepStatus::Done | StepStatus::Failed => {
+                let started = self.started?;
+                Some(*self.took.get_or_insert_with(|| started.elapsed()))
+            }
+            StepStatus::Pending | StepStatus::Skipped => {
+                self.started = None;
+                self.took = None;
+                None
+            }
+        }
+    }
+}
+
+/// Draws the `{step_glyph}` key, which marks a finished step as done or skipped.
+struct StepGlyph<V> {
+    status: StatusSource<V>,
+    skipped: Arc<AtomicBool>,
+}
+
+impl<V> BarExtension<V> for StepGlyph<V> {
+    fn record(&self, model: &V) {
+        let skipped = (self.status)(model) == StepStatus::Skipped;
+        self.skipped.store(skipped, Ordering::Relaxed);
+    }
+
+    fn style(&self, style: ProgressStyle, theme: &Theme) -> ProgressStyle {
+        let glyph = |glyph: &str, color: &Option<String>| match color {
+            Some(color) => Style::from_dotted_str(color).apply_to(glyph).to_string(),
+            None => glyph.to_string(),
+        };
+        let done = glyph(&theme.finished_glyph, &theme.finished_color);
+        let skipped_glyph = glyph(&theme.skipped_glyph, &theme.muted_color);
+        let skipped = self.skipped.clone();
+        style.with_key(
+            "step_glyph",
+            move |_: &ProgressState, w: &mut dyn fmt::Write| {
+                let _ = if skipped.load(Ordering::Relaxed) {
+                    w.write_str(&skipped_glyph)
+                } else {
+                    w.write_str(&done)
+                };
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ProgressBarTree, TerminalCapabilities,
+        helpers::{tree::checklist, widgets::*},
+    };
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    #[test]
+    fn test_checklist() {
+        let term = InMemoryTerm::new(10, 40);
+        let step =
+            |index: usize, name: &str| Step::new(name, move |model: &Vec<StepStatus>| model[index]);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![checklist(vec![
+                step(0, "resolve"),
+                step(1, "download").with_details(message_static("file.tar")),
+                step(2, "verify"),
+                step(3, "install"),
+            ])],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        use StepStatus::*;
+        tree.tick(&vec![Running, Pending, Pending, Pending]);
+        tree.tick(&vec![Done, Running, Pending, Pending]);
+        let contents = term.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        // The times taken vary, so only their presence is checked
+        assert!(lines[0].starts_with("+ resolve (") && lines[0].ends_with("s)"));
+        assert!(lines[1].contains(" download ("));
+        assert_eq!(lines[2..], ["  file.tar", ". verify", ". install"]);
+
+        tree.tick(&vec![Done, Failed, Skipped, Pending]);
+        let contents = term.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert!(lines[1].starts_with("x download ("));
+        assert_eq!(lines[2..], ["- verify", ". install"]);
+
+        // A retried step is drawn running again, with its details
+        tree.tick(&vec![Done, Running, Skipped, Pending]);
+        let contents = term.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert!(!lines[1].starts_with("x ") && lines[1].contains(" download ("));
+        assert_eq!(lines[2..], ["  file.tar", "- verify", ". install"]);
+    }
+
+    #[test]
+    fn test_checklist_counts() {
+        let term = InMemoryTerm::new(10, 40);
+        let step =
+            |index: usize, name: &str| Step::new(name, move |model: &Vec<StepStatus>| model[index]);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                checklist(vec![
+                    step(0, "resolve"),
+                    step(1, "verify"),
+                    step(2, "install"),
+                ])
+                .with_header(header_counts("steps")),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        use StepStatus::*;
+        // Pending steps are drawn, but aren't counted as done
+        tree.tick(&vec![Done, Pending, Pending]);
+        let contents = term.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with(" steps 1/3 done"));
+
+        tree.tick(&vec![Done, Skipped, Done]);
+        assert_eq!(term.contents().lines().next(), Some("+ steps 3/3 done"));
+    }
+
+    #[test]
+    fn test_step_timing() {
+        use StepStatus::*;
+        // A step that's done without being seen running has no time
+        let mut timing = StepTiming::default();
+        assert_eq!(timing.update(Done), None);
+
+        // A retried step is timed from when it's run again
+        timing.update(Running);
+        std::thread::sleep(Duration::from_millis(20));
+        let took = timing.update(Done).unwrap();
+        assert!(took >= Duration::from_millis(20));
+        assert!(timing.update(Running).unwrap() < took);
+        assert!(timing.update(Done).unwrap() < took);
+    }
+}
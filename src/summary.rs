@@ -0,0 +1,413 @@
+use crate::{DisplayState, ProgressBarState, render::Resolved};
+use std::time::Duration;
+
+/// The combined state of a group's children, which a group header is bound to. See
+/// [`crate::ProgressBarTreeContainer::with_header`].
+///
+/// Each child counts once however many bars it contains, with the state of its busiest bar.
+/// Children that are hidden, or only show a static message, aren't counted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GroupSummary {
+    pub total: usize,
+    pub not_started: usize,
+    pub active: usize,
+    pub finished: usize,
+    pub failed: usize,
+    /// The positions and lengths of every bar in the group with one, added together
+    pub position_and_len: Option<(u64, u64)>,
+    /// The average progress of the children between 0 and 1, each weighted equally. Finished
+    /// children count as complete, and children without a length as not started until they finish.
+    pub fraction: f64,
+    /// How long since the first child started, stopping once they're all done. Only tracked for
+    /// groups with a header or summary.
+    pub elapsed: Option<Duration>,
+}
+
+impl GroupSummary {
+    pub(crate) fn from_children<'a>(children: impl IntoIterator<Item = &'a Resolved>) -> Self {
+        let mut summary = GroupSummary::default();
+        let mut fractions = 0.0;
+        for child in children {
+            let Some(state) = child_state(child) else {
+                continue;
+            };
+            summary.total += 1;
+            match state {
+                DisplayState::NotStarted => summary.not_started += 1,
+                DisplayState::Active(_) => summary.active += 1,
+                DisplayState::Finished(_) => summary.finished += 1,
+                DisplayState::Failed(_) => summary.failed += 1,
+                DisplayState::FinishedAndHidden => unreachable!("hidden children aren't counted"),
+            }
+
+            let position_and_len = child_position_and_len(child);
+            if let Some((position, length)) = position_and_len {
+                let (total_position, total_length) = summary.position_and_len.unwrap_or((0, 0));
+                summary.position_and_len = Some((total_position + position, total_length + length));
+            }
+            fractions += match (state, position_and_len) {
+                (state, _) if state.is_finished() => 1.0,
+                (_, Some((position, length))) if length > 0 => position as f64 / length as f64,
+                _ => 0.0,
+            };
+        }
+        if summary.total > 0 {
+            summary.fraction = fractions / summary.total as f64;
+        }
+        summary
+    }
+
+    /// Children that have finished, successfully or not.
+    pub fn done(&self) -> usize {
+        self.finished + self.failed
+    }
+
+    /// The worst state of the children: failed if any has failed, active while any is in
+    /// progress, finished once all are, and not started before any has started.
+    pub fn state(&self) -> DisplayState<()> {
+        if self.failed > 0 {
+            DisplayState::Failed(())
+        } else if self.total == 0 || self.not_started == self.total {
+            DisplayState::NotStarted
+        } else if self.finished == self.total {
+            DisplayState::Finished(())
+        } else {
+            DisplayState::Active(())
+        }
+    }
+
+    /// `label` followed by a count of the children done, e.g. `"tests 3/7 done, 1 failed"`, in the
+    /// summary's state.
+    pub fn counts_message(&self, label: &str) -> DisplayState<String> {
+        let mut message = format!("{label} {}/{} done", self.done(), self.total);
+        if self.failed > 0 {
+            message.push_str(&format!(", {} failed", self.failed));
+        }
+        self.state().map(|_| message)
+    }
+
+    /// How many children are done and how long they took, e.g. `"50 tasks done in 1m12s"`, in the
+    /// summary's state. The noun is written as given, so should be plural.
+    pub fn done_message(&self, noun: &str) -> DisplayState<String> {
+        let mut message = format!("{} {noun} done", self.done());
+        if self.failed > 0 {
+            message.push_str(&format!(", {} failed", self.failed));
+        }
+        if let Some(elapsed) = self.elapsed {
+            message.push_str(&format!(" in {}", format_duration(elapsed)));
+        }
+        self.state().map(|_| message)
+    }
+
+    /// A count of the children by state, e.g. `"and 42 more (12 queued, 30 done)"`, used for the
+    /// line standing in for the children left out by `max_visible`.
+    pub fn overflow_message(&self) -> String {
+        let counts: Vec<String> = [
+            (self.active, "active"),
+            (self.failed, "failed"),
+            (self.not_started, "queued"),
+            (self.finished, "done"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, state)| format!("{count} {state}"))
+        .collect();
+        format!("and {} more ({})", self.total, counts.join(", "))
+    }
+
+    /// `label` with the children's positions and lengths added together, in the summary's state.
+    /// Uses the count of children done when none of them have a length.
+    pub fn progress(&self, label: &str) -> DisplayState<ProgressBarState> {
+        let (position, length) = self
+            .position_and_len
+            .unwrap_or((self.done() as u64, self.total as u64));
+        self.state()
+            .map(|_| ProgressBarState::new(Some(label.to_string()), None, position, length))
+    }
+}
+
+/// Formats a duration like `4.2s`, `1m12s` or `2h05m00s`.
+pub fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..60 => format!("{:.1}s", duration.as_secs_f64()),
+        60..3600 => format!("{}m{:02}s", seconds / 60, seconds % 60),
+        _ => format!(
+            "{}h{:02}m{:02}s",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        ),
+    }
+}
+
+/// The state a child is counted in, or `None` if it isn't counted.
+fn child_state(child: &Resolved) -> Option<DisplayState<()>> {
+    match child {
+        Resolved::Leaf(DisplayState::FinishedAndHidden, _) => None,
+        Resolved::Leaf(_, info) if info.is_static => None,
+        Resolved::Leaf(state, _) => Some(state.clone().map(|_| ())),
+        Resolved::Group(group) => {
+            let summary = GroupSummary::from_children(&group.children);
+            (summary.total > 0).then(|| summary.state())
+        }
+    }
+}
+
+fn child_position_and_len(child: &Resolved) -> Option<(u64, u64)> {
+    match child {
+        Resolved::Leaf(
+            DisplayState::Active(progress)
+            | DisplayState::Finished(progress)
+            | DisplayState::Failed(progress),
+            _,
+        ) => progress.position_and_len(),
+        Resolved::Leaf(..) => None,
+        Resolved::Group(group) => GroupSummary::from_children(&group.children).position_and_len,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        CollapsePolicy, ProgressBarTree, ProgressBarTreeContainer, TerminalCapabilities,
+        helpers::{tree::nested, widgets::*},
+        progress_bar_bindable::BarInfo,
+        render::ResolvedGroup,
+    };
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    #[test]
+    fn test_summary_from_children() {
+        let progress = |position, length| {
+            Resolved::Leaf(
+                DisplayState::Active(ProgressBarState::new(None, None, position, length)),
+                BarInfo::default(),
+            )
+        };
+        let summary = GroupSummary::from_children(&[
+            progress(5, 10),
+            Resolved::Leaf(DisplayState::NotStarted, BarInfo::default()),
+            Resolved::Group(Box::new(ResolvedGroup {
+                children: vec![
+                    progress(10, 10),
+                    Resolved::Leaf(
+                        DisplayState::Failed(ProgressBarState::default()),
+                        BarInfo::default(),
+                    ),
+                ],
+                slots: vec![0, 1],
+                order: None,
+                header: None,
+                summary: None,
+                overflow: None,
+                collapsed: false,
+                continues_previous: false,
+                shown: None,
+            })),
+            // Neither hidden nor static children are counted
+            Resolved::Leaf(DisplayState::FinishedAndHidden, BarInfo::default()),
+            Resolved::Leaf(
+                DisplayState::Finished(ProgressBarState::default()),
+                BarInfo {
+                    is_static: true,
+                    ..BarInfo::default()
+                },
+            ),
+        ]);
+
+        assert_eq!(
+            (summary.total, summary.not_started, summary.active),
+            (3, 1, 1)
+        );
+        assert_eq!((summary.finished, summary.failed), (0, 1));
+        assert_eq!(summary.position_and_len, Some((15, 20)));
+        assert_eq!(summary.fraction, (0.5 + 0.0 + 1.0) / 3.0);
+        assert_eq!(summary.state(), DisplayState::Failed(()));
+        assert_eq!(
+            summary.counts_message("build"),
+            DisplayState::Failed("build 1/3 done, 1 failed".to_string())
+        );
+    }
+
+    #[test]
+    fn test_header_follows_children() {
+        let term = InMemoryTerm::new(10, 40);
+        let step = |index: usize| {
+            message(move |states: &Vec<DisplayState<String>>| states[index].clone()).into()
+        };
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                nested(vec![step(0), step(1), step(2)])
+                    .with_header(message(|summary: &GroupSummary| {
+                        summary.counts_message("build")
+                    }))
+                    .with_collapse(CollapsePolicy::WhenFinished),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        let active = |name: &str| DisplayState::Active(name.to_string());
+        let finished = |name: &str| DisplayState::Finished(name.to_string());
+
+        tree.tick(&vec![DisplayState::NotStarted; 3]);
+        assert_eq!(term.contents(), "");
+
+        tree.tick(&vec![finished("a"), active("b"), DisplayState::NotStarted]);
+        assert_eq!(term.contents(), "build 1/3 done\n  a\n  b");
+
+        tree.tick(&vec![
+            finished("a"),
+            DisplayState::Failed("b".to_string()),
+            active("c"),
+        ]);
+        assert_eq!(
+            term.contents(),
+            "x build 2/3 done, 1 failed\n  a\n  x b\n  c"
+        );
+
+        tree.tick(&vec![finished("a"), finished("b"), finished("c")]);
+        assert_eq!(term.contents(), "build 3/3 done");
+    }
+
+    #[test]
+    fn test_header_leaves_out_hidden_children() {
+        let term = InMemoryTerm::new(10, 40);
+        let step = |index: usize| -> ProgressBarTreeContainer<Vec<DisplayState<String>>> {
+            message(move |states: &Vec<DisplayState<String>>| states[index].clone()).into()
+        };
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                nested(vec![
+                    message_static("note").into(),
+                    step(0),
+                    step(1),
+                    step(2).with_display_condition(Box::new(|_| false)),
+                ])
+                .with_header(message(|summary: &GroupSummary| {
+                    summary.counts_message("build")
+                }))
+                .with_collapse(CollapsePolicy::WhenFinished),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        let active = |name: &str| DisplayState::Active(name.to_string());
+        let finished = |name: &str| DisplayState::Finished(name.to_string());
+
+        // The hidden child would finish the group if it were counted
+        tree.tick(&vec![finished("a"), active("b"), finished("c")]);
+        assert_eq!(term.contents(), "build 1/2 done\n  note\n  a\n  b");
+
+        tree.tick(&vec![finished("a"), finished("b"), active("c")]);
+        assert_eq!(term.contents(), "build 2/2 done");
+    }
+
+    #[test]
+    fn test_collapse_to_summary() {
+        struct Model {
+            steps: Vec<DisplayState<String>>,
+            folded: bool,
+        }
+
+        let term = InMemoryTerm::new(10, 40);
+        let step = |index: usize| message(move |model: &Model| model.steps[index].clone()).into();
+        let steps = || vec![step(0), step(1)];
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                message_static("fast").into(),
+                nested(steps())
+                    .with_summary(summary_done("steps"))
+                    .with_collapse(CollapsePolicy::WhenFinished),
+                message_static("slow").into(),
+                nested(steps())
+                    .with_summary(summary_done("steps"))
+                    .with_collapse(CollapsePolicy::WhenFinishedFor(Duration::from_secs(60))),
+                message_static("manual").into(),
+                nested(steps())
+                    .with_summary(summary_done("steps"))
+                    .with_collapse(CollapsePolicy::Manual(Box::new(|model: &Model| {
+                        model.folded
+                    }))),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        let finished = |name: &str| DisplayState::Finished(name.to_string());
+        tree.tick(&Model {
+            steps: vec![finished("a"), DisplayState::Active("b".to_string())],
+            folded: true,
+        });
+        let contents = term.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(
+            lines[..7],
+            ["fast", "  a", "  b", "slow", "  a", "  b", "manual"]
+        );
+        // The spinner frame and time taken vary
+        assert!(lines[7].contains(" 1 steps done in "));
+
+        tree.tick(&Model {
+            steps: vec![finished("a"), finished("b")],
+            folded: false,
+        });
+        let contents = term.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert!(lines[1].starts_with("  + 2 steps done in "));
+        assert_eq!(lines[2..], ["slow", "  a", "  b", "manual", "  a", "  b"]);
+    }
+
+    #[test]
+    fn test_summary_done() {
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![summary_done("tasks").into()],
+        )
+        .with_capabilities(TerminalCapabilities {
+            unicode: true,
+            color: false,
+        });
+
+        tree.tick(&GroupSummary {
+            total: 50,
+            finished: 50,
+            elapsed: Some(Duration::from_secs(72)),
+            ..GroupSummary::default()
+        });
+        assert_eq!(term.contents(), "✔ 50 tasks done in 1m12s");
+    }
+
+    #[test]
+    fn test_collapse_without_summary() {
+        let term = InMemoryTerm::new(10, 40);
+        let step = |index: usize| {
+            message(move |states: &Vec<DisplayState<String>>| states[index].clone()).into()
+        };
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                message_static("build").into(),
+                nested(vec![step(0), step(1)]).with_collapse(CollapsePolicy::WhenFinished),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        let finished = |name: &str| DisplayState::Finished(name.to_string());
+        tree.tick(&vec![finished("a"), DisplayState::Active("b".to_string())]);
+        assert_eq!(
+            term.contents(),
+            "build
+  a
+  b"
+        );
+
+        // Without a header or summary the group collapses to nothing
+        tree.tick(&vec![finished("a"), finished("b")]);
+        assert_eq!(term.contents(), "build");
+    }
+}
@@ -0,0 +1,368 @@
+//! A live table, with a header line and one bar per row of the model.
+
+use crate::{
+    DisplayState, LeafUpdate, ProgressBarBindable, ProgressBarState, ProgressBarTreeContainer,
+    dynamic::{DynamicChildren, DynamicGroup},
+    group::GroupOptions,
+    multiprogress_bindable::{BarPath, MultiProgressWrapper},
+    render::{Resolved, draw_bar},
+    theme::{Theme, Widget},
+};
+use std::sync::{Arc, Mutex};
+
+// The width of a progress column that fits its cells, like indicatif's `{bar}`
+const DEFAULT_BAR_WIDTH: usize = 20;
+
+/// How wide a [`Column`] is drawn. Cells that don't fit are truncated with the theme's
+/// `overflow_glyph`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColumnWidth {
+    /// As wide as the widest cell or the header. Progress columns are 20 wide.
+    #[default]
+    Fit,
+    Fixed(usize),
+    /// As wide as the widest cell or the header, up to the given width
+    Max(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Align {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+/// A column of a [`Table`], showing one cell per row.
+pub struct Column<R> {
+    header: String,
+    width: ColumnWidth,
+    align: Align,
+    cell: Cell<R>,
+}
+
+enum Cell<R> {
+    Text(Box<dyn Fn(&R) -> String + Send>),
+    // The fraction of the bar to fill, between 0 and 1
+    Progress(Box<dyn Fn(&R) -> f64 + Send>),
+}
+
+impl<R> Column<R> {
+    /// A column of text, e.g. a name or a count.
+    pub fn text(header: impl Into<String>, cell: impl Fn(&R) -> String + Send + 'static) -> Self {
+        Self::new(header, Cell::Text(Box::new(cell)))
+    }
+
+    /// A column of progress bars, filled to the fraction returned for each row, drawn with the
+    /// theme's `bar_chars`.
+    pub fn progress(
+        header: impl Into<String>,
+        fraction: impl Fn(&R) -> f64 + Send + 'static,
+    ) -> Self {
+        Self::new(header, Cell::Progress(Box::new(fraction)))
+    }
+
+    fn new(header: impl Into<String>, cell: Cell<R>) -> Self {
+        Self {
+            header: header.into(),
+            width: ColumnWidth::default(),
+            align: Align::default(),
+            cell,
+        }
+    }
+
+    pub fn with_width(mut self, width: ColumnWidth) -> Self {
+        self.width = width;
+        self
+    }
+
+    pub fn with_align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+
+    fn width(&self, cells: &[String]) -> usize {
+        let fit = || {
+            let cells = match self.cell {
+                Cell::Text(_) => cells
+                    .iter()
+                    .map(|cell| console::measure_text_width(cell))
+                    .max(),
+                Cell::Progress(_) => Some(DEFAULT_BAR_WIDTH),
+            };
+            cells
+                .unwrap_or(0)
+                .max(console::measure_text_width(&self.header))
+        };
+        match self.width {
+            ColumnWidth::Fit => fit(),
+            ColumnWidth::Fixed(width) => width,
+            ColumnWidth::Max(width) => fit().min(width),
+        }
+    }
+}
+
+/// A header line followed by one bar per row returned by the model, with the cells of each
+/// column padded to the same width:
+///
+/// ```
+/// use declaratif::{ProgressBarTreeContainer, table::{Align, Column, Table}};
+///
+/// #[derive(Clone)]
+/// struct Worker { name: String, done: u64, total: u64 }
+///
+/// let table: ProgressBarTreeContainer<Vec<Worker>> = Table::new(Vec::clone)
+///     .with_column(Column::text("worker", |worker: &Worker| worker.name.clone()))
+///     .with_column(
+///         Column::text("items", |worker: &Worker| worker.done.to_string())
+///             .with_align(Align::Right),
+///     )
+///     .with_column(Column::progress("progress", |worker: &Worker| {
+///         worker.done as f64 / worker.total.max(1) as f64
+///     }))
+///     .into();
+/// ```
+pub struct Table<V: Send + Sync, R> {
+    rows: RowSource<V, R>,
+    columns: Vec<Column<R>>,
+    separator: String,
+    state: Mutex<TableState<V>>,
+}
+
+type RowSource<V, R> = Box<dyn Fn(&V) -> Vec<R> + Send>;
+
+struct TableState<V: Send + Sync> {
+    parent: Option<(BarPath, MultiProgressWrapper)>,
+    theme: Arc<Theme>,
+    header: ProgressBarTreeContainer<V>,
+    rows: Vec<ProgressBarTreeContainer<V>>,
+}
+
+impl<V: Send + Sync, R> Table<V, R> {
+    pub fn new(rows: impl Fn(&V) -> Vec<R> + Send + 'static) -> Self {
+        Self {
+            rows: Box::new(rows),
+            columns: Vec::new(),
+            separator: "  ".to_string(),
+            state: Mutex::new(TableState {
+                parent: None,
+                theme: Arc::new(Theme::default()),
+                header: line(),
+                rows: Vec::new(),
+            }),
+        }
+    }
+
+    pub fn with_column(mut self, column: Column<R>) -> Self {
+        self.columns.push(column);
+        self
+    }
+
+    /// Sets what's drawn between columns, two spaces by default.
+    pub fn with_separator(mut self, separator: impl Into<String>) -> Self {
+        self.separator = separator.into();
+        self
+    }
+
+    /// The header line followed by a line for each row.
+    fn lines(&self, rows: &[R], theme: &Theme) -> Vec<String> {
+        let columns: Vec<(&Column<R>, Vec<String>)> = self
+            .columns
+            .iter()
+            .map(|column| {
+                let cells = match &column.cell {
+                    Cell::Text(cell) => rows.iter().map(cell).collect(),
+                    Cell::Progress(_) => Vec::new(),
+                };
+                (column, cells)
+            })
+            .collect();
+        let widths: Vec<usize> = columns
+            .iter()
+            .map(|(column, cells)| column.width(cells))
+            .collect();
+
+        let pad = |text: &str, width: usize, align: Align| {
+            let align = match align {
+                Align::Left => console::Alignment::Left,
+                Align::Center => console::Alignment::Center,
+                Align::Right => console::Alignment::Right,
+            };
+            console::pad_str(text, width, align, Some(&theme.overflow_glyph)).into_owned()
+        };
+        let join = |cells: Vec<String>| cells.join(&self.separator).trim_end().to_string();
+
+        let header = columns
+            .iter()
+            .zip(&widths)
+            .map(|((column, _), width)| pad(&column.header, *width, column.align))
+            .collect();
+        let rows = rows.iter().enumerate().map(|(row_index, row)| {
+            columns
+                .iter()
+                .zip(&widths)
+                .map(|((column, cells), width)| match &column.cell {
+                    Cell::Text(_) => pad(&cells[row_index], *width, column.align),
+                    Cell::Progress(fraction) => draw_bar(fraction(row), *width, &theme.bar_chars),
+                })
+                .collect()
+        });
+        std::iter::once(header).chain(rows).map(join).collect()
+    }
+}
+
+impl<V: Send + Sync + 'static, R: 'static> From<Table<V, R>> for ProgressBarTreeContainer<V> {
+    fn from(table: Table<V, R>) -> Self {
+        ProgressBarTreeContainer::Dynamic(DynamicGroup::new(table), None, GroupOptions::default())
+    }
+}
+
+impl<V: Send + Sync, R> DynamicChildren<V> for Table<V, R> {
+    fn reparent(&mut self, path: &BarPath, multiprogress: MultiProgressWrapper) {
+        let state = self.state.get_mut().unwrap();
+        for (slot, line) in std::iter::once(&mut state.header)
+            .chain(state.rows.iter_mut())
+            .enumerate()
+        {
+            let mut line_path = path.clone();
+            line_path.push(slot);
+            line.reparent(&mut line_path, multiprogress.clone());
+        }
+        state.parent = Some((path.clone(), multiprogress));
+    }
+
+    fn set_theme(&mut self, theme: &Arc<Theme>) {
+        let state = self.state.get_mut().unwrap();
+        state.header.set_theme(theme);
+        for row in state.rows.iter_mut() {
+            row.set_theme(theme);
+        }
+        state.theme = theme.clone();
+    }
+
+    fn resolve(
+        &self,
+        model: &V,
+        can_display: bool,
+        path: &mut Vec<usize>,
+        observer: &mut dyn FnMut(&LeafUpdate<'_>),
+    ) -> Vec<(usize, Resolved)> {
+        let mut state = self.state.lock().unwrap();
+        let rows = if can_display {
+            (self.rows)(model)
+        } else {
+            Vec::new()
+        };
+
+        // Rows that have gone are hidden straight away, like a `for_each`
+        while state.rows.len() > rows.len() {
+            let row = state.rows.pop().unwrap();
+            path.push(state.rows.len() + 1);
+            row.tick_inner(model, false, path, observer);
+            path.pop();
+        }
+        while state.rows.len() < rows.len() {
+            let mut row = line();
+            row.set_theme(&state.theme);
+            if let Some((parent_path, multiprogress)) = &state.parent {
+                let mut row_path = parent_path.clone();
+                row_path.push(state.rows.len() + 1);
+                row.reparent(&mut row_path, multiprogress.clone());
+            }
+            state.rows.push(row);
+        }
+
+        let lines = self.lines(&rows, &state.theme);
+        std::iter::once(&state.header)
+            .chain(&state.rows)
+            .zip(lines.into_iter().map(Some).chain(std::iter::repeat(None)))
+            .enumerate()
+            .map(|(slot, (line, text))| {
+                let ProgressBarTreeContainer::Leaf(bar, _) = line else {
+                    unreachable!("Table lines are single bars")
+                };
+                let state = match text {
+                    Some(text) if can_display => {
+                        DisplayState::Active(ProgressBarState::from_message(text))
+                    }
+                    _ => DisplayState::FinishedAndHidden,
+                };
+                let state = bar.resolve_state(state);
+                path.push(slot);
+                observer(&LeafUpdate {
+                    id: bar.id(),
+                    path,
+                    state: &state,
+                });
+                path.pop();
+                (slot, Resolved::Leaf(state, bar.info()))
+            })
+            .collect()
+    }
+
+    fn visit(&self, visit: &mut dyn FnMut(&[&ProgressBarTreeContainer<V>])) {
+        let state = self.state.lock().unwrap();
+        let lines: Vec<_> = std::iter::once(&state.header).chain(&state.rows).collect();
+        visit(&lines);
+    }
+}
+
+// A bar for one line of the table
+fn line<V: Send + Sync>() -> ProgressBarTreeContainer<V> {
+    ProgressBarBindable::themed(Widget::Message).into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProgressBarTree, TerminalCapabilities};
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    type Row = (&'static str, u64, u64);
+
+    #[test]
+    fn test_table() {
+        let term = InMemoryTerm::new(10, 60);
+        let table = Table::new(|rows: &Vec<Row>| rows.clone())
+            .with_column(Column::text("worker", |(name, _, _): &Row| {
+                name.to_string()
+            }))
+            .with_column(
+                Column::text("items", |(_, done, _): &Row| done.to_string())
+                    .with_align(Align::Right),
+            )
+            .with_column(
+                Column::progress("progress", |(_, done, total): &Row| {
+                    *done as f64 / *total as f64
+                })
+                .with_width(ColumnWidth::Fixed(8)),
+            )
+            .with_column(
+                Column::text("note", |(name, _, _): &Row| format!("{name} is running"))
+                    .with_width(ColumnWidth::Max(10)),
+            );
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![table.into()],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        tree.tick(&vec![("alpha", 4, 8), ("b", 12, 16)]);
+        assert_eq!(
+            term.contents(),
+            [
+                "worker  items  progress  note",
+                "alpha       4  ####>---  alpha i...",
+                "b          12  ######>-  b is ru...",
+            ]
+            .join("\n")
+        );
+
+        // Rows that have gone are removed
+        tree.tick(&vec![("b", 16, 16)]);
+        assert_eq!(
+            term.contents(),
+            "worker  items  progress  note\nb          16  ########  b is ru..."
+        );
+    }
+}
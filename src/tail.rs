@@ -0,0 +1,181 @@
+use crate::{
+    DisplayState, LeafUpdate, ProgressBarBindable, ProgressBarState, ProgressBarTreeContainer,
+    dynamic::DynamicChildren,
+    multiprogress_bindable::{BarPath, MultiProgressWrapper},
+    render::Resolved,
+    theme::{Theme, Widget},
+    writer::LogTail,
+};
+use std::sync::Arc;
+
+/// How a tail of output lines is drawn, see [`crate::helpers::tree::tail`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TailOptions {
+    pub rows: usize,
+    /// Draws blank rows until there are enough lines to fill them, so the lines below the tail
+    /// don't move as it fills
+    pub fill: bool,
+    /// Draws all but the newest line in the theme's `muted_color`
+    pub dim_older: bool,
+}
+
+impl TailOptions {
+    /// `rows` rows, filled with blank lines and with the older lines dimmed.
+    pub fn new(rows: usize) -> Self {
+        Self {
+            rows,
+            fill: true,
+            dim_older: true,
+        }
+    }
+}
+
+type ModelLines<V> = Box<dyn for<'a> Fn(&'a V) -> &'a [String] + Send>;
+
+/// Where a [`Tail`] takes its lines from. Only the lines drawn are copied.
+pub(crate) enum LineSource<V> {
+    Model(ModelLines<V>),
+    Log(LogTail),
+}
+
+impl<V> LineSource<V> {
+    fn last(&self, model: &V, rows: usize) -> Vec<String> {
+        match self {
+            LineSource::Model(lines) => {
+                let lines = lines(model);
+                lines[lines.len().saturating_sub(rows)..].to_vec()
+            }
+            LineSource::Log(tail) => tail.last(rows),
+        }
+    }
+}
+
+/// A fixed number of rows showing the last lines returned by the model, oldest first. Each line is
+/// cut off at the edge of the terminal rather than wrapping.
+pub(crate) struct Tail<V: Send + Sync> {
+    lines: LineSource<V>,
+    options: TailOptions,
+    rows: Vec<ProgressBarTreeContainer<V>>,
+}
+
+impl<V: Send + Sync> Tail<V> {
+    pub(crate) fn new(lines: LineSource<V>, options: TailOptions) -> Self {
+        Self {
+            lines,
+            options,
+            rows: (0..options.rows)
+                .map(|_| ProgressBarBindable::themed(Widget::LogLine).into())
+                .collect(),
+        }
+    }
+}
+
+impl<V: Send + Sync> DynamicChildren<V> for Tail<V> {
+    fn reparent(&mut self, path: &BarPath, multiprogress: MultiProgressWrapper) {
+        for (slot, row) in self.rows.iter_mut().enumerate() {
+            let mut row_path = path.clone();
+            row_path.push(slot);
+            row.reparent(&mut row_path, multiprogress.clone());
+        }
+    }
+
+    fn set_theme(&mut self, theme: &Arc<Theme>) {
+        for row in self.rows.iter_mut() {
+            row.set_theme(theme);
+        }
+    }
+
+    fn resolve(
+        &self,
+        model: &V,
+        can_display: bool,
+        path: &mut Vec<usize>,
+        observer: &mut dyn FnMut(&LeafUpdate<'_>),
+    ) -> Vec<(usize, Resolved)> {
+        let lines = if can_display {
+            self.lines.last(model, self.options.rows)
+        } else {
+            Vec::new()
+        };
+        let newest = lines.len().saturating_sub(1);
+
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(slot, row)| {
+                let ProgressBarTreeContainer::Leaf(bar, _) = row else {
+                    unreachable!("Tail rows are single bars")
+                };
+                // Indicatif doesn't draw empty lines, so blank ones are drawn as a space
+                let line = lines.get(slot).map(|line| {
+                    if line.is_empty() {
+                        ProgressBarState::from_message(" ")
+                    } else {
+                        ProgressBarState::from_message(line.clone())
+                    }
+                });
+                // Older lines are drawn as finished, which the theme dims
+                let state = match line {
+                    Some(line) if self.options.dim_older && slot < newest => {
+                        DisplayState::Finished(line)
+                    }
+                    Some(line) => DisplayState::Active(line),
+                    None if can_display && self.options.fill => {
+                        DisplayState::Active(ProgressBarState::from_message(" "))
+                    }
+                    None if can_display => DisplayState::NotStarted,
+                    None => DisplayState::FinishedAndHidden,
+                };
+                let state = bar.resolve_state(state);
+                path.push(slot);
+                observer(&LeafUpdate {
+                    id: bar.id(),
+                    path,
+                    state: &state,
+                });
+                path.pop();
+                (slot, Resolved::Leaf(state, bar.info()))
+            })
+            .collect()
+    }
+
+    fn visit(&self, visit: &mut dyn FnMut(&[&ProgressBarTreeContainer<V>])) {
+        let rows: Vec<_> = self.rows.iter().collect();
+        visit(&rows);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        ProgressBarTree, TailOptions, TerminalCapabilities,
+        helpers::{tree::tail, widgets::*},
+    };
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    #[test]
+    fn test_tail() {
+        let term = InMemoryTerm::new(10, 12);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                tail(|lines: &Vec<String>| lines.as_slice(), TailOptions::new(3)),
+                message_static("Building").into(),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        // Blank rows keep the line below in place until the tail fills
+        tree.tick(&vec!["one".to_string()]);
+        assert_eq!(term.contents(), "one\n\n\nBuilding");
+
+        let lines = ["one", "two", "three", "a much longer line"];
+        tree.tick(&lines.map(String::from).to_vec());
+        assert_eq!(term.contents(), "two\nthree\na much longe\nBuilding");
+
+        // Blank lines in the output still take up their row
+        let lines = ["one", "", "three"];
+        tree.tick(&lines.map(String::from).to_vec());
+        assert_eq!(term.contents(), "one\n\nthree\nBuilding");
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{
+    DisplayState, ProgressBarBindable, ProgressBarState, ProgressBarTreeContainer, Widget,
+    helpers::tree::{for_each, group},
+};
+use std::{
+    sync::RwLock,
+    time::{Duration, Instant},
+};
+
+/// The lifecycle of a task, as reported by whatever is doing the work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "json",
+    derive(serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum TaskState {
+    #[default]
+    Pending,
+    Active,
+    Finished,
+    Failed,
+    Hidden,
+}
+
+/// A change to a single task. Fields left as `None` keep their previous value, so an update only
+/// needs to carry what changed.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "json", derive(serde::Deserialize))]
+pub struct TaskUpdate {
+    pub id: String,
+    pub parent: Option<String>,
+    pub state: Option<TaskState>,
+    pub message: Option<String>,
+    pub pos: Option<u64>,
+    pub len: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Task {
+    pub id: String,
+    pub parent: Option<String>,
+    pub state: TaskState,
+    pub message: Option<String>,
+    pub position: Option<u64>,
+    pub length: Option<u64>,
+    /// When the task first became active
+    pub started: Option<Instant>,
+    /// When the task first finished or failed
+    pub finished: Option<Instant>,
+}
+
+impl Task {
+    fn new(id: String) -> Self {
+        Self {
+            id,
+            parent: None,
+            state: TaskState::Pending,
+            message: None,
+            position: None,
+            length: None,
+            started: None,
+            finished: None,
+        }
+    }
+
+    /// Time between the task starting and finishing, or until now if it is still running.
+    pub fn elapsed(&self) -> Option<Duration> {
+        let started = self.started?;
+        Some(self.finished.unwrap_or_else(Instant::now) - started)
+    }
+
+    /// The message if one has been set, otherwise the id.
+    pub fn label(&self) -> &str {
+        self.message.as_deref().unwrap_or(&self.id)
+    }
+}
+
+/// A view model holding a set of tasks that can be updated from any thread, typically from a
+/// reader parsing progress output (see [`crate::protocol`]) while a render thread ticks
+/// [`task_tree`].
+#[derive(Default)]
+pub struct TaskModel {
+    tasks: RwLock<Vec<Task>>,
+}
+
+impl TaskModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn apply(&self, update: TaskUpdate) {
+        let mut tasks = self.tasks.write().unwrap();
+        let index = match tasks.iter().position(|task| task.id == update.id) {
+            Some(index) => index,
+            None => {
+                tasks.push(Task::new(update.id));
+                tasks.len() - 1
+            }
+        };
+
+        let task = &mut tasks[index];
+        if update.parent.is_some() {
+            task.parent = update.parent;
+        }
+        if update.message.is_some() {
+            task.message = update.message;
+        }
+        if update.pos.is_some() {
+            task.position = update.pos;
+        }
+        if update.len.is_some() {
+            task.length = update.len;
+        }
+        if let Some(state) = update.state {
+            task.state = state;
+            if state == TaskState::Active {
+                task.started.get_or_insert_with(Instant::now);
+            }
+            if matches!(state, TaskState::Finished | TaskState::Failed) {
+                let now = Instant::now();
+                task.started.get_or_insert(now);
+                task.finished.get_or_insert(now);
+            }
+        }
+    }
+
+    /// Removes a task and all of its descendants.
+    pub fn remove(&self, id: &str) {
+        let mut tasks = self.tasks.write().unwrap();
+        let mut removed = vec![id.to_string()];
+        while let Some(parent) = removed.pop() {
+            tasks.retain(|task| {
+                if task.id == parent {
+                    false
+                } else if task.parent.as_deref() == Some(parent.as_str()) {
+                    removed.push(task.id.clone());
+                    false
+                } else {
+                    true
+                }
+            });
+        }
+    }
+
+    pub fn task(&self, id: &str) -> Option<Task> {
+        let tasks = self.tasks.read().unwrap();
+        tasks.iter().find(|task| task.id == id).cloned()
+    }
+
+    /// Ids of the tasks directly under `parent` (or the top level tasks for `None`), in the order
+    /// they were first seen.
+    pub fn children(&self, parent: Option<&str>) -> Vec<String> {
+        let tasks = self.tasks.read().unwrap();
+        tasks
+            .iter()
+            .filter(|task| task.parent.as_deref() == parent)
+            .map(|task| task.id.clone())
+            .collect()
+    }
+
+    pub fn all_finished(&self) -> bool {
+        let tasks = self.tasks.read().unwrap();
+        tasks.iter().all(|task| {
+            matches!(
+                task.state,
+                TaskState::Finished | TaskState::Failed | TaskState::Hidden
+            )
+        })
+    }
+
+    fn display_state(&self, id: &str) -> DisplayState<Task> {
+        match self.task(id) {
+            None => DisplayState::FinishedAndHidden,
+            Some(task) => match task.state {
+                TaskState::Pending => DisplayState::NotStarted,
+                TaskState::Active => DisplayState::Active(task),
+                TaskState::Finished => DisplayState::Finished(task),
+                TaskState::Failed => DisplayState::Failed(task),
+                TaskState::Hidden => DisplayState::FinishedAndHidden,
+            },
+        }
+    }
+}
+
+/// Renders every task in a [`TaskModel`] as an indented tree, following the parent ids. Tasks with
+/// a length show a progress bar while active, others a spinner. Tasks whose parent is unknown are
+/// not shown until the parent appears. Subtasks are nested, so they get guides if the tree has
+/// them enabled.
+pub fn task_tree() -> ProgressBarTreeContainer<TaskModel> {
+    for_each(
+        |model: &TaskModel| model.children(None),
+        |id: &String| task_subtree(id.clone()),
+    )
+}
+
+fn task_subtree(id: String) -> ProgressBarTreeContainer<TaskModel> {
+    let spinner_id = id.clone();
+    let bar_id = id.clone();
+
+    group(vec![
+        // Shown for active tasks without a length, and for all finished tasks
+        ProgressBarBindable::themed(Widget::Spinner)
+            .with_id(id.clone())
+            .bind_progress(
+                move |model: &TaskModel| match model.display_state(&spinner_id) {
+                    DisplayState::Active(task) if task.length.is_some() => DisplayState::NotStarted,
+                    state => state.map(|task| task_progress(&task)),
+                },
+            )
+            .into(),
+        ProgressBarBindable::themed(Widget::MessageBar)
+            .bind_progress(
+                move |model: &TaskModel| match model.display_state(&bar_id) {
+                    DisplayState::Active(task) if task.length.is_some() => {
+                        DisplayState::Active(task_progress(&task))
+                    }
+                    _ => DisplayState::NotStarted,
+                },
+            )
+            .into(),
+        for_each(
+            move |model: &TaskModel| model.children(Some(&id)),
+            move |child: &String| task_subtree(child.clone()),
+        )
+        .nested(),
+    ])
+}
+
+fn task_progress(task: &Task) -> ProgressBarState {
+    match task.length {
+        Some(length) => ProgressBarState::new(
+            Some(task.label().to_string()),
+            None,
+            task.position.unwrap_or(0),
+            length,
+        ),
+        None => ProgressBarState::from_message(task.label()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{LeafUpdate, ProgressBarTree, ProgressSink};
+    use indicatif::{MultiProgress, ProgressDrawTarget};
+    use std::sync::{Arc, Mutex};
+
+    type VisibleLeaf = (Vec<usize>, String);
+
+    #[derive(Clone, Default)]
+    struct VisibleLeaves(Arc<Mutex<Vec<VisibleLeaf>>>);
+
+    impl ProgressSink for VisibleLeaves {
+        fn leaf_ticked(&mut self, leaf: &LeafUpdate<'_>) {
+            if let DisplayState::Active(progress)
+            | DisplayState::Finished(progress)
+            | DisplayState::Failed(progress) = leaf.state
+            {
+                self.0.lock().unwrap().push((
+                    leaf.path.to_vec(),
+                    progress.message().unwrap_or_default().to_string(),
+                ));
+            }
+        }
+    }
+
+    impl VisibleLeaves {
+        fn take(&self) -> Vec<VisibleLeaf> {
+            std::mem::take(&mut *self.0.lock().unwrap())
+        }
+    }
+
+    fn update(id: &str, parent: Option<&str>, state: TaskState) -> TaskUpdate {
+        TaskUpdate {
+            id: id.to_string(),
+            parent: parent.map(str::to_string),
+            state: Some(state),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_task_model_updates_merge() {
+        let model = TaskModel::new();
+        model.apply(TaskUpdate {
+            id: "build".to_string(),
+            message: Some("Building".to_string()),
+            len: Some(10),
+            ..Default::default()
+        });
+        model.apply(TaskUpdate {
+            id: "build".to_string(),
+            state: Some(TaskState::Active),
+            pos: Some(3),
+            ..Default::default()
+        });
+
+        let task = model.task("build").unwrap();
+        assert_eq!(task.state, TaskState::Active);
+        assert_eq!(task.label(), "Building");
+        assert_eq!((task.position, task.length), (Some(3), Some(10)));
+        assert!(task.started.is_some());
+        assert!(!model.all_finished());
+
+        model.apply(update("test", Some("build"), TaskState::Active));
+        model.apply(update("lint", Some("build"), TaskState::Finished));
+        assert_eq!(model.children(Some("build")), vec!["test", "lint"]);
+
+        model.remove("build");
+        assert!(model.task("test").is_none());
+        assert!(model.all_finished());
+    }
+
+    #[test]
+    fn test_task_tree_follows_model() {
+        let visible = VisibleLeaves::default();
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden()),
+            vec![task_tree()],
+        )
+        .with_sink(visible.clone());
+        let model = TaskModel::new();
+
+        tree.tick(&model);
+        assert!(visible.take().is_empty());
+
+        model.apply(update("a", None, TaskState::Active));
+        model.apply(update("a.1", Some("a"), TaskState::Active));
+        model.apply(update("b", None, TaskState::Finished));
+        tree.tick(&model);
+        assert_eq!(
+            visible.take(),
+            vec![
+                (vec![0, 0, 0], "a".to_string()),
+                (vec![0, 0, 2, 0, 0], "a.1".to_string()),
+                (vec![0, 1, 0], "b".to_string()),
+            ]
+        );
+
+        // Removing a task keeps the slots of the others stable
+        model.remove("a");
+        model.apply(update("c", None, TaskState::Active));
+        tree.tick(&model);
+        assert_eq!(
+            visible.take(),
+            vec![
+                (vec![0, 1, 0], "b".to_string()),
+                (vec![0, 2, 0], "c".to_string()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,637 @@
+use indicatif::{ProgressState, ProgressStyle, style::TemplateError};
+use std::fmt::{self, Write};
+
+/// Which of a [`Theme`]'s styles a bar uses. Bars built from a template or an explicit
+/// `ProgressStyle` are `Custom` and keep the styles they were given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Widget {
+    Custom,
+    /// Just the message, e.g. `message` and `message_static`
+    Message,
+    /// A spinner followed by the message
+    Spinner,
+    /// The message followed by a spinner
+    SpinnerPost,
+    /// A full width bar with the position and length
+    Bar,
+    /// A spinner, the message and a fixed width bar with the position and length
+    MessageBar,
+    /// The message after the overflow glyph, for the line standing in for hidden children
+    Overflow,
+    /// The prefix as a label followed by the message as its value, e.g. the lines of a panel
+    Field,
+    /// The message cut off at the edge of the terminal, muted once finished, e.g. the lines of a
+    /// tail of output
+    LogLine,
+    /// The message followed by the `{history}` key, see
+    /// [`crate::ProgressBarBindable::with_history`]
+    Chart,
+    /// A checklist step: the pending glyph before it starts, a spinner while it runs, and the
+    /// `{step_glyph}` key once finished, which shows whether it was done or skipped
+    Step,
+    /// A spinner and the message followed by the `{timer}` key, see
+    /// [`crate::ProgressBarBindable::with_timer`]
+    Timer,
+}
+
+impl Widget {
+    /// Every widget a [`Theme`] styles.
+    const THEMED: [Widget; 11] = [
+        Widget::Message,
+        Widget::Spinner,
+        Widget::SpinnerPost,
+        Widget::Bar,
+        Widget::MessageBar,
+        Widget::Overflow,
+        Widget::Field,
+        Widget::LogLine,
+        Widget::Chart,
+        Widget::Step,
+        Widget::Timer,
+    ];
+}
+
+/// The state a style is used for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StyleKind {
+    /// Not started yet. Only widgets with a pending style, e.g. [`Widget::Step`], are drawn
+    /// before they start.
+    Pending,
+    Active,
+    Finished,
+    Failed,
+}
+
+/// The look shared by every themed widget in a tree, set with
+/// [`crate::ProgressBarTree::with_theme`].
+///
+/// Colours use `console`'s style syntax (e.g. `"cyan"`, `"bold.red"`, `"cyan/blue"` for bars),
+/// and are left out of the templates entirely when `None`. A theme built by hand can be checked
+/// with [`Theme::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Theme {
+    /// Spinner animation frames. As with indicatif's `tick_chars`, the last frame is shown once
+    /// the spinner finishes.
+    pub spinner_frames: String,
+    /// Characters used to draw bars, as with indicatif's `progress_chars`
+    pub bar_chars: String,
+    /// Characters used to draw sparklines, from lowest to highest
+    pub spark_chars: String,
+    pub finished_glyph: String,
+    pub failed_glyph: String,
+    /// Marks checklist steps that haven't started or were skipped
+    pub pending_glyph: String,
+    pub skipped_glyph: String,
+    /// Starts the line standing in for children left out by `max_visible`
+    pub overflow_glyph: String,
+    /// Colour of spinners and bars
+    pub accent_color: Option<String>,
+    pub finished_color: Option<String>,
+    pub failed_color: Option<String>,
+    /// Colour of secondary text, e.g. the older lines of a tail of output
+    pub muted_color: Option<String>,
+    /// Repeated once per indentation level
+    pub indent: String,
+    /// Guides drawn before the first line of a child of a nested group, when the tree has guides
+    /// enabled. `guide_last` is used for the group's last visible child.
+    pub guide_branch: String,
+    pub guide_last: String,
+    /// Guides drawn before the later lines of a child, and under it for deeper levels
+    pub guide_continue: String,
+    pub guide_blank: String,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::unicode()
+    }
+}
+
+impl Theme {
+    /// Unicode spinners, smooth bars and colour.
+    pub fn unicode() -> Self {
+        Self {
+            spinner_frames: "⠁⠂⠄⡀⢀⠠⠐⠈ ".to_string(),
+            bar_chars: "█▉▊▋▌▍▎▏ ".to_string(),
+            spark_chars: "▁▂▃▄▅▆▇█".to_string(),
+            finished_glyph: "✔".to_string(),
+            failed_glyph: "✘".to_string(),
+            pending_glyph: "○".to_string(),
+            skipped_glyph: "↓".to_string(),
+            overflow_glyph: "…".to_string(),
+            accent_color: Some("cyan".to_string()),
+            finished_color: Some("green".to_string()),
+            failed_color: Some("red".to_string()),
+            muted_color: Some("dim".to_string()),
+            indent: "  ".to_string(),
+            guide_branch: "├─ ".to_string(),
+            guide_last: "└─ ".to_string(),
+            guide_continue: "│  ".to_string(),
+            guide_blank: "   ".to_string(),
+        }
+    }
+
+    /// Only ASCII characters and no colour, for terminals that can't render anything else.
+    pub fn ascii() -> Self {
+        Self {
+            spinner_frames: "-\\|/ ".to_string(),
+            bar_chars: "#>-".to_string(),
+            spark_chars: "_.-=^#".to_string(),
+            finished_glyph: "+".to_string(),
+            failed_glyph: "x".to_string(),
+            pending_glyph: ".".to_string(),
+            skipped_glyph: "-".to_string(),
+            overflow_glyph: "...".to_string(),
+            accent_color: None,
+            finished_color: None,
+            failed_color: None,
+            muted_color: None,
+            indent: "  ".to_string(),
+            guide_branch: "|- ".to_string(),
+            guide_last: "`- ".to_string(),
+            guide_continue: "|  ".to_string(),
+            guide_blank: "   ".to_string(),
+        }
+    }
+
+    /// No animation or colour, with lighter glyphs.
+    pub fn minimal() -> Self {
+        Self {
+            spinner_frames: "  ".to_string(),
+            bar_chars: "━─".to_string(),
+            spark_chars: "▁▂▃▄▅▆▇█".to_string(),
+            finished_glyph: "✓".to_string(),
+            failed_glyph: "✗".to_string(),
+            pending_glyph: "○".to_string(),
+            skipped_glyph: "↓".to_string(),
+            overflow_glyph: "…".to_string(),
+            accent_color: None,
+            finished_color: None,
+            failed_color: None,
+            muted_color: None,
+            indent: "  ".to_string(),
+            guide_branch: "├─ ".to_string(),
+            guide_last: "└─ ".to_string(),
+            guide_continue: "│  ".to_string(),
+            guide_blank: "   ".to_string(),
+        }
+    }
+
+    /// Replaces anything the terminal can't display with the [`Theme::ascii`] equivalent, and
+    /// drops the colours if the terminal shouldn't get any.
+    pub fn for_terminal(mut self, capabilities: TerminalCapabilities) -> Self {
+        if !capabilities.unicode {
+            let ascii = Theme::ascii();
+            for (field, fallback) in [
+                (&mut self.spinner_frames, ascii.spinner_frames),
+                (&mut self.bar_chars, ascii.bar_chars),
+                (&mut self.spark_chars, ascii.spark_chars),
+                (&mut self.finished_glyph, ascii.finished_glyph),
+                (&mut self.failed_glyph, ascii.failed_glyph),
+                (&mut self.pending_glyph, ascii.pending_glyph),
+                (&mut self.skipped_glyph, ascii.skipped_glyph),
+                (&mut self.overflow_glyph, ascii.overflow_glyph),
+                (&mut self.indent, ascii.indent),
+                (&mut self.guide_branch, ascii.guide_branch),
+                (&mut self.guide_last, ascii.guide_last),
+                (&mut self.guide_continue, ascii.guide_continue),
+                (&mut self.guide_blank, ascii.guide_blank),
+            ] {
+                if !field.is_ascii() {
+                    *field = fallback;
+                }
+            }
+        }
+        if !capabilities.color {
+            self.accent_color = None;
+            self.finished_color = None;
+            self.failed_color = None;
+            self.muted_color = None;
+        }
+        self
+    }
+
+    /// Checks that every themed widget can be styled, failing on spinner frames or bar chars
+    /// indicatif can't draw, or a colour that breaks a template.
+    pub fn validate(&self) -> Result<(), ThemeError> {
+        for widget in Widget::THEMED {
+            for kind in [
+                StyleKind::Pending,
+                StyleKind::Active,
+                StyleKind::Finished,
+                StyleKind::Failed,
+            ] {
+                self.style(widget, kind, 0)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The style for `widget` in the given state, indented by `indent` levels. Returns `None`
+    /// for custom widgets, and for the pending state of widgets that aren't drawn before they
+    /// start.
+    pub fn style(
+        &self,
+        widget: Widget,
+        kind: StyleKind,
+        indent: usize,
+    ) -> Result<Option<ProgressStyle>, ThemeError> {
+        // indicatif panics on fewer than two characters, or bar chars of different widths
+        if self.spinner_frames.chars().count() < 2 {
+            return Err(ThemeError::Chars(self.spinner_frames.clone()));
+        }
+        let mut widths = self
+            .bar_chars
+            .chars()
+            .map(|c| console::measure_text_width(&c.to_string()));
+        let width = widths.next();
+        if self.bar_chars.chars().count() < 2 || widths.any(|other| Some(other) != width) {
+            return Err(ThemeError::Chars(self.bar_chars.clone()));
+        }
+
+        let Some(template) = self.template(widget, kind) else {
+            return Ok(None);
+        };
+        let indent = self.indent.repeat(indent);
+        let mut style = ProgressStyle::with_template(&template)
+            .map_err(|error| ThemeError::Template { template, error })?
+            .tick_chars(&self.spinner_frames)
+            .progress_chars(&self.bar_chars)
+            .with_key("indent", move |_: &ProgressState, w: &mut dyn Write| {
+                let _ = w.write_str(&indent);
+            });
+        for (key, glyph) in [
+            ("finished_glyph", &self.finished_glyph),
+            ("failed_glyph", &self.failed_glyph),
+            ("pending_glyph", &self.pending_glyph),
+            ("skipped_glyph", &self.skipped_glyph),
+            ("overflow_glyph", &self.overflow_glyph),
+        ] {
+            let glyph = glyph.clone();
+            style = style.with_key(key, move |_: &ProgressState, w: &mut dyn Write| {
+                let _ = w.write_str(&glyph);
+            });
+        }
+        Ok(Some(style))
+    }
+
+    fn template(&self, widget: Widget, kind: StyleKind) -> Option<String> {
+        // Keys with a width already have the `:` separating the format spec
+        let key = |key: &str, color: &Option<String>| match color {
+            Some(color) if key.contains(':') => format!("{{{key}.{color}}}"),
+            Some(color) => format!("{{{key}:.{color}}}"),
+            None => format!("{{{key}}}"),
+        };
+        let spinner = key("spinner", &self.accent_color);
+        let finished = key("finished_glyph", &self.finished_color);
+        let failed = key("failed_glyph", &self.failed_color);
+
+        let template = match (widget, kind) {
+            (Widget::Custom, _) => return None,
+            (Widget::Step, StyleKind::Pending) => format!(
+                "{} {}",
+                key("pending_glyph", &self.muted_color),
+                key("msg", &self.muted_color)
+            ),
+            (_, StyleKind::Pending) => return None,
+            (Widget::Message, StyleKind::Failed) => format!("{failed} {{msg}}"),
+            (Widget::Message, _) => "{msg}".to_string(),
+            (Widget::Spinner, StyleKind::Active) => format!("{spinner} {{msg}}"),
+            (Widget::Spinner, StyleKind::Finished) => format!("{finished} {{msg}}"),
+            (Widget::Spinner, StyleKind::Failed) => format!("{failed} {{msg}}"),
+            (Widget::SpinnerPost, StyleKind::Active) => format!("{{msg}} {spinner}"),
+            (Widget::SpinnerPost, StyleKind::Finished) => format!("{{msg}} {finished}"),
+            (Widget::SpinnerPost, StyleKind::Failed) => format!("{{msg}} {failed}"),
+            (Widget::Bar, StyleKind::Active) => {
+                format!("{} {{pos}}/{{len}}", key("wide_bar", &self.accent_color))
+            }
+            (Widget::Bar, StyleKind::Finished) => {
+                format!("{finished} {{wide_bar}} {{pos}}/{{len}}")
+            }
+            (Widget::Bar, StyleKind::Failed) => format!("{failed} {{wide_bar}} {{pos}}/{{len}}"),
+            (Widget::MessageBar, StyleKind::Active) => format!(
+                "{spinner} {{msg}} {} {{pos}}/{{len}}",
+                key("bar:30", &self.accent_color)
+            ),
+            (Widget::MessageBar, StyleKind::Finished) => format!("{finished} {{msg}}"),
+            (Widget::MessageBar, StyleKind::Failed) => format!("{failed} {{msg}}"),
+            (Widget::Overflow, _) => "{overflow_glyph} {msg}".to_string(),
+            (Widget::Field, StyleKind::Failed) => {
+                format!("{{prefix}} {}", key("msg", &self.failed_color))
+            }
+            (Widget::Field, _) => "{prefix} {msg}".to_string(),
+            (Widget::LogLine, StyleKind::Finished) => key("wide_msg", &self.muted_color),
+            (Widget::LogLine, _) => "{wide_msg}".to_string(),
+            (Widget::Chart, StyleKind::Active) => {
+                format!("{{msg}} {}", key("history", &self.accent_color))
+            }
+            (Widget::Chart, StyleKind::Finished) => format!("{finished} {{msg}} {{history}}"),
+            (Widget::Chart, StyleKind::Failed) => format!("{failed} {{msg}} {{history}}"),
+            (Widget::Step, StyleKind::Active) => format!("{spinner} {{msg}}"),
+            (Widget::Step, StyleKind::Finished) => "{step_glyph} {msg}".to_string(),
+            (Widget::Step, StyleKind::Failed) => format!("{failed} {{msg}}"),
+            (Widget::Timer, StyleKind::Active) => format!("{spinner} {{msg}} {{timer}}"),
+            (Widget::Timer, StyleKind::Finished) => format!("{finished} {{msg}} {{timer}}"),
+            (Widget::Timer, StyleKind::Failed) => format!("{failed} {{msg}} {{timer}}"),
+        };
+        Some(format!("{{indent}}{template}"))
+    }
+}
+
+/// Why a [`Theme`] can't be used.
+#[derive(Debug)]
+pub enum ThemeError {
+    /// Spinner frames or bar chars need at least two characters, and bar chars must all be the
+    /// same width
+    Chars(String),
+    /// A template built from the theme, most likely broken by one of its colours
+    Template {
+        template: String,
+        error: TemplateError,
+    },
+}
+
+impl fmt::Display for ThemeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ThemeError::Chars(chars) => {
+                write!(
+                    f,
+                    "`{chars}` needs at least two characters of the same width"
+                )
+            }
+            ThemeError::Template { template, error } => {
+                write!(f, "invalid theme template `{template}`: {error}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ThemeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ThemeError::Template { error, .. } => Some(error),
+            ThemeError::Chars(_) => None,
+        }
+    }
+}
+
+/// What the terminal can display, used to make a [`Theme`] safe for it. Only the tree's theme is
+/// changed: bars given their own style keep their characters and colours.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TerminalCapabilities {
+    pub unicode: bool,
+    pub color: bool,
+}
+
+impl Default for TerminalCapabilities {
+    fn default() -> Self {
+        Self::full()
+    }
+}
+
+impl TerminalCapabilities {
+    pub fn full() -> Self {
+        Self {
+            unicode: true,
+            color: true,
+        }
+    }
+
+    pub fn ascii_only() -> Self {
+        Self {
+            unicode: false,
+            color: false,
+        }
+    }
+
+    /// Detects the capabilities from the environment:
+    /// - `NO_COLOR` set to anything but an empty string disables colour
+    /// - `TERM=dumb` disables both unicode and colour
+    /// - A locale (`LC_ALL`, `LC_CTYPE` or `LANG`, in that order) that isn't UTF-8 disables unicode
+    ///
+    /// Without a locale, unicode is assumed except on Windows, where it's only assumed in
+    /// terminals that announce themselves (e.g. Windows Terminal), not the legacy console.
+    pub fn detect() -> Self {
+        Self::from_env(|name| std::env::var(name).ok())
+    }
+
+    fn from_env(var: impl Fn(&str) -> Option<String>) -> Self {
+        let set = |name: &str| var(name).filter(|value| !value.is_empty());
+        let dumb = set("TERM").is_some_and(|term| term == "dumb");
+
+        let locale = ["LC_ALL", "LC_CTYPE", "LANG"].into_iter().find_map(set);
+        let unicode = match locale {
+            Some(locale) => {
+                let locale = locale.to_ascii_lowercase();
+                locale.contains("utf-8") || locale.contains("utf8")
+            }
+            None if cfg!(windows) => set("WT_SESSION").is_some() || set("TERM_PROGRAM").is_some(),
+            None => true,
+        };
+
+        Self {
+            unicode: unicode && !dumb,
+            color: set("NO_COLOR").is_none() && !dumb,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        DisplayState, ProgressBarBindable, ProgressBarTree,
+        helpers::{tree::for_each, widgets::*},
+    };
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    struct Model {
+        done: bool,
+    }
+
+    fn status(name: &'static str, failed: bool) -> impl Fn(&Model) -> DisplayState<String> {
+        move |model: &Model| match (model.done, failed) {
+            (false, _) => DisplayState::Active(name.to_string()),
+            (true, false) => DisplayState::Finished(name.to_string()),
+            (true, true) => DisplayState::Failed(name.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_tree_theme_applies_to_all_themed_bars() {
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::<Model>::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                spinner(status("build", false)).into(),
+                spinner_post(status("test", true)).into(),
+                // Children built after the theme is set still use it
+                for_each(
+                    |_: &Model| vec!["lint"],
+                    |name| {
+                        ProgressBarBindable::themed(Widget::Spinner)
+                            .with_indent(1)
+                            .bind_message(status(name, false))
+                            .into()
+                    },
+                ),
+                from_template_str("custom {msg}")
+                    .bind_message(status("own style", false))
+                    .into(),
+            ],
+        )
+        .with_theme(Theme::ascii())
+        .unwrap();
+
+        tree.tick(&Model { done: false });
+        // The spinner frame depends on how often the bar has been drawn
+        let contents = term.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert!(lines[0].ends_with(" build"));
+        assert!(lines[1].starts_with("test "));
+        assert!(lines[2].starts_with("  ") && lines[2].ends_with(" lint"));
+        assert_eq!(lines[3], "custom own style");
+        assert!(contents.is_ascii());
+
+        tree.tick(&Model { done: true });
+        assert_eq!(
+            term.contents(),
+            "+ build\ntest x\n  + lint\ncustom own style"
+        );
+    }
+
+    #[test]
+    fn test_templates_follow_theme() {
+        let unicode = Theme::unicode();
+        assert_eq!(
+            unicode
+                .template(Widget::Spinner, StyleKind::Active)
+                .unwrap(),
+            "{indent}{spinner:.cyan} {msg}"
+        );
+        assert_eq!(
+            unicode
+                .template(Widget::SpinnerPost, StyleKind::Failed)
+                .unwrap(),
+            "{indent}{msg} {failed_glyph:.red}"
+        );
+
+        let ascii = Theme::ascii();
+        assert_eq!(
+            ascii.template(Widget::Bar, StyleKind::Active).unwrap(),
+            "{indent}{wide_bar} {pos}/{len}"
+        );
+        assert!(ascii.template(Widget::Custom, StyleKind::Active).is_none());
+        assert_eq!(
+            Theme::unicode()
+                .template(Widget::LogLine, StyleKind::Finished)
+                .unwrap(),
+            "{indent}{wide_msg:.dim}"
+        );
+
+        // Every built in theme must produce valid styles
+        for theme in [Theme::unicode(), Theme::ascii(), Theme::minimal()] {
+            for widget in Widget::THEMED {
+                for kind in [StyleKind::Active, StyleKind::Finished, StyleKind::Failed] {
+                    assert!(theme.style(widget, kind, 1).unwrap().is_some());
+                }
+                // Only steps are drawn before they start
+                let pending = theme.style(widget, StyleKind::Pending, 1).unwrap();
+                assert_eq!(pending.is_some(), widget == Widget::Step);
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_themes_are_rejected() {
+        let validate = |theme: Theme| theme.validate().unwrap_err().to_string();
+        assert_eq!(
+            validate(Theme {
+                spinner_frames: "*".to_string(),
+                ..Theme::ascii()
+            }),
+            "`*` needs at least two characters of the same width"
+        );
+        assert_eq!(
+            validate(Theme {
+                bar_chars: "#语-".to_string(),
+                ..Theme::ascii()
+            }),
+            "`#语-` needs at least two characters of the same width"
+        );
+        assert!(
+            validate(Theme {
+                accent_color: Some("cyan}".to_string()),
+                ..Theme::ascii()
+            })
+            .starts_with("invalid theme template")
+        );
+
+        let tree = ProgressBarTree::<Model>::new(MultiProgress::new(), vec![]);
+        assert!(
+            tree.with_theme(Theme {
+                bar_chars: String::new(),
+                ..Theme::unicode()
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_detect_capabilities() {
+        let detect = |vars: &[(&str, &str)]| {
+            TerminalCapabilities::from_env(|name| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            })
+        };
+
+        assert_eq!(
+            detect(&[("LANG", "en_GB.UTF-8"), ("TERM", "xterm-256color")]),
+            TerminalCapabilities::full()
+        );
+        assert_eq!(
+            detect(&[("LANG", "en_GB.UTF-8"), ("TERM", "dumb")]),
+            TerminalCapabilities::ascii_only()
+        );
+        assert_eq!(
+            detect(&[("LANG", "en_US.utf8"), ("NO_COLOR", "1")]),
+            TerminalCapabilities {
+                unicode: true,
+                color: false
+            }
+        );
+        // An empty NO_COLOR is ignored, and LC_ALL takes priority over LANG
+        assert_eq!(
+            detect(&[("NO_COLOR", ""), ("LC_ALL", "C"), ("LANG", "en_GB.UTF-8")]),
+            TerminalCapabilities {
+                unicode: false,
+                color: true
+            }
+        );
+    }
+
+    #[test]
+    fn test_theme_for_terminal() {
+        let theme = Theme::unicode().for_terminal(TerminalCapabilities::ascii_only());
+        assert_eq!(theme, Theme::ascii());
+
+        let theme = Theme::unicode().for_terminal(TerminalCapabilities {
+            unicode: true,
+            color: false,
+        });
+        assert_eq!(theme.finished_glyph, "✔");
+        assert!(theme.accent_color.is_none());
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::<Model>::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![spinner(status("build", false)).into()],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+        tree.tick(&Model { done: true });
+        assert_eq!(term.contents(), "+ build");
+    }
+}
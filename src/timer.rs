@@ -0,0 +1,325 @@
+use crate::{
+    DisplayState, ProgressBarState, extension::BarExtension, summary::format_duration, theme::Theme,
+};
+use indicatif::{ProgressBar, ProgressState, ProgressStyle, WeakProgressBar};
+use std::{
+    fmt,
+    sync::{Arc, Condvar, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+/// How often timer widgets redraw between ticks, so their times count smoothly.
+pub(crate) const TIMER_TICK: Duration = Duration::from_millis(100);
+
+/// What a bar's timer key shows, see [`crate::ProgressBarBindable::with_timer`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Timer {
+    /// The time left until the instant, rounded up and stopping at zero, e.g. `"retrying in 7s"`
+    Remaining(TimeFormat),
+    /// The time since the instant, e.g. `"running for 1m12s"`
+    Elapsed(TimeFormat),
+}
+
+/// How a [`Timer`] writes its time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimeFormat {
+    /// As [`crate::format_duration`] does, e.g. `"4.2s"` or `"1m12s"`
+    #[default]
+    Short,
+    /// Whole seconds, e.g. `"7s"` or `"142s"`
+    Seconds,
+    /// Minutes and seconds like a clock, e.g. `"00:42"`, with the hours once there are any, e.g.
+    /// `"1:02:03"`
+    Clock,
+}
+
+impl TimeFormat {
+    pub fn format(self, duration: Duration) -> String {
+        let seconds = duration.as_secs();
+        match self {
+            TimeFormat::Short => format_duration(duration),
+            TimeFormat::Seconds => format!("{seconds}s"),
+            TimeFormat::Clock if seconds < 3600 => {
+                format!("{:02}:{:02}", seconds / 60, seconds % 60)
+            }
+            TimeFormat::Clock => format!(
+                "{}:{:02}:{:02}",
+                seconds / 3600,
+                seconds / 60 % 60,
+                seconds % 60
+            ),
+        }
+    }
+}
+
+/// An instant taken from the model on each tick, which the template key it's bound to measures
+/// from whenever the bar is drawn.
+pub(crate) struct BoundTimer<F> {
+    key: &'static str,
+    timer: Timer,
+    instant: F,
+    current: Arc<Mutex<Option<Instant>>>,
+}
+
+impl<F> BoundTimer<F> {
+    pub(crate) fn new(key: &'static str, timer: Timer, instant: F) -> Self {
+        Self {
+            key,
+            timer,
+            instant,
+            current: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// The template key writing the time, or nothing without an instant.
+    fn draw(&self) -> impl Fn(&ProgressState, &mut dyn fmt::Write) + Clone + Send + Sync + 'static {
+        let (timer, current) = (self.timer, self.current.clone());
+        move |_: &ProgressState, w: &mut dyn fmt::Write| {
+            let Some(instant) = *current.lock().unwrap() else {
+                return;
+            };
+            let now = Instant::now();
+            let time = match timer {
+                Timer::Remaining(format) => {
+                    let remaining = instant.saturating_duration_since(now);
+                    // Rounded up so it only reads zero once the instant has passed
+                    let remaining = match (format, remaining.subsec_nanos()) {
+                        (TimeFormat::Short, _) | (_, 0) => remaining,
+                        _ => Duration::from_secs(remaining.as_secs() + 1),
+                    };
+                    format.format(remaining)
+                }
+                Timer::Elapsed(format) => format.format(now.saturating_duration_since(instant)),
+            };
+            let _ = w.write_str(&time);
+        }
+    }
+}
+
+impl<V, F: Fn(&V) -> Option<Instant> + Send> BarExtension<V> for BoundTimer<F> {
+    fn record(&self, model: &V) {
+        *self.current.lock().unwrap() = (self.instant)(model);
+    }
+
+    fn style(&self, style: ProgressStyle, _theme: &Theme) -> ProgressStyle {
+        style.with_key(self.key, self.draw())
+    }
+}
+
+/// An instant taken from the model on each tick, at which the bar it's bound to finishes, whether
+/// or not the model is ticked then.
+pub(crate) struct BoundDeadline<F> {
+    instant: F,
+    current: Mutex<Option<Instant>>,
+    waiter: Arc<Waiter>,
+}
+
+/// Waits for a bar's deadline on its own thread, which is started when the bar is first armed and
+/// stops once it's disarmed or has finished the bar. Moving the deadline re-arms the same thread.
+#[derive(Default)]
+struct Waiter {
+    state: Mutex<WaiterState>,
+    changed: Condvar,
+}
+
+#[derive(Default)]
+struct WaiterState {
+    armed: Option<Armed>,
+    running: bool,
+}
+
+struct Armed {
+    deadline: Instant,
+    bar: WeakProgressBar,
+    finish_style: Option<ProgressStyle>,
+}
+
+impl<F> BoundDeadline<F> {
+    pub(crate) fn new(instant: F) -> Self {
+        Self {
+            instant,
+            current: Mutex::new(None),
+            waiter: Arc::new(Waiter::default()),
+        }
+    }
+
+    /// Finishes `bar` with `finish_style` once the deadline passes, unless it's moved, cleared or
+    /// disarmed by then.
+    fn arm(&self, bar: &ProgressBar, finish_style: Option<ProgressStyle>) {
+        let Some(deadline) = *self.current.lock().unwrap() else {
+            self.disarm();
+            return;
+        };
+        let mut state = self.waiter.state.lock().unwrap();
+        state.armed = Some(Armed {
+            deadline,
+            bar: bar.downgrade(),
+            finish_style,
+        });
+        self.waiter.changed.notify_one();
+        if !state.running {
+            state.running = true;
+            let waiter = self.waiter.clone();
+            thread::spawn(move || waiter.wait());
+        }
+    }
+
+    /// Stops waiting for the deadline, e.g. once the bar has finished anyway.
+    fn disarm(&self) {
+        self.waiter.state.lock().unwrap().armed = None;
+        self.waiter.changed.notify_one();
+    }
+}
+
+impl<V, F: Fn(&V) -> Option<Instant> + Send> BarExtension<V> for BoundDeadline<F> {
+    fn record(&self, model: &V) {
+        *self.current.lock().unwrap() = (self.instant)(model);
+    }
+
+    // Only active bars wait for their deadline, anything else has finished or isn't counting yet
+    fn drawn(
+        &self,
+        bar: Option<&ProgressBar>,
+        state: &DisplayState<ProgressBarState>,
+        finish_style: Option<&ProgressStyle>,
+    ) {
+        match (bar, state) {
+            (Some(bar), DisplayState::Active(_)) => self.arm(bar, finish_style.cloned()),
+            _ => self.disarm(),
+        }
+    }
+}
+
+impl Waiter {
+    fn wait(&self) {
+        let mut state = self.state.lock().unwrap();
+        let armed = loop {
+            let Some(deadline) = state.armed.as_ref().map(|armed| armed.deadline) else {
+                state.running = false;
+                return;
+            };
+            let now = Instant::now();
+            if now >= deadline {
+                break state.armed.take().unwrap();
+            }
+            state = self.changed.wait_timeout(state, deadline - now).unwrap().0;
+        };
+        state.running = false;
+        drop(state);
+
+        let Some(bar) = armed.bar.upgrade().filter(|bar| !bar.is_finished()) else {
+            return;
+        };
+        if let Some(finish_style) = armed.finish_style {
+            bar.set_style(finish_style);
+        }
+        bar.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProgressBarTree, TerminalCapabilities, helpers::widgets::*};
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    #[test]
+    fn test_time_format() {
+        let duration = Duration::from_secs(3723);
+        assert_eq!(TimeFormat::Short.format(duration), "1h02m03s");
+        assert_eq!(TimeFormat::Seconds.format(duration), "3723s");
+        assert_eq!(TimeFormat::Clock.format(duration), "1:02:03");
+        assert_eq!(TimeFormat::Clock.format(Duration::from_secs(42)), "00:42");
+    }
+
+    #[test]
+    fn test_timers() {
+        struct Model {
+            retry_at: Option<Instant>,
+            started: Option<Instant>,
+        }
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![
+                countdown(
+                    "retrying in",
+                    |model: &Model| model.retry_at,
+                    TimeFormat::Clock,
+                )
+                .into(),
+                stopwatch(
+                    "running for",
+                    |model: &Model| model.started,
+                    TimeFormat::Short,
+                )
+                .into(),
+            ],
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only());
+
+        let now = Instant::now();
+        // Instants can't go back further than the clock's start, e.g. just after boot
+        let started = now.checked_sub(Duration::from_secs(75));
+        tree.tick(&Model {
+            retry_at: Some(now + Duration::from_secs(42)),
+            started,
+        });
+        // Spinner frames vary with time, so only the text after them is checked
+        let contents = term.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert!(lines[0].ends_with(" retrying in 00:42"));
+        if started.is_some() {
+            assert!(lines[1].ends_with(" running for 1m15s"));
+        }
+
+        tree.tick(&Model {
+            retry_at: Some(now),
+            started: None,
+        });
+        assert_eq!(term.contents(), "+ retrying in 00:00");
+
+        // Restarting the countdown makes it active again
+        tree.tick(&Model {
+            retry_at: Some(Instant::now() + Duration::from_secs(7)),
+            started: None,
+        });
+        assert!(term.contents().ends_with(" retrying in 00:07"));
+        assert!(!term.contents().starts_with('+'));
+
+        // The countdown finishes at its deadline without waiting for the next tick
+        tree.tick(&Model {
+            retry_at: Some(Instant::now() + Duration::from_millis(50)),
+            started: None,
+        });
+        std::thread::sleep(Duration::from_millis(300));
+        assert_eq!(term.contents(), "+ retrying in 00:00");
+    }
+
+    #[test]
+    fn test_deadline_keeps_one_waiter() {
+        let deadline = BoundDeadline::new(|at: &Instant| Some(*at));
+        let bar = ProgressBar::hidden();
+        // A deadline moved on every tick re-arms the same waiter, which holds the other reference
+        for _ in 0..20 {
+            deadline.record(&(Instant::now() + Duration::from_millis(50)));
+            deadline.arm(&bar, None);
+        }
+        assert_eq!(Arc::strong_count(&deadline.waiter), 2);
+
+        thread::sleep(Duration::from_millis(200));
+        assert!(bar.is_finished());
+        assert_eq!(Arc::strong_count(&deadline.waiter), 1);
+
+        // A disarmed deadline leaves the bar alone
+        let bar = ProgressBar::hidden();
+        deadline.record(&(Instant::now() + Duration::from_millis(50)));
+        deadline.arm(&bar, None);
+        deadline.disarm();
+        thread::sleep(Duration::from_millis(200));
+        assert!(!bar.is_finished());
+        assert_eq!(Arc::strong_count(&deadline.waiter), 1);
+    }
+}
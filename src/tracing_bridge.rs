@@ -0,0 +1,43 @@
+use crate::ProgressWriter;
+use tracing_subscriber::fmt::MakeWriter;
+
+/// Lets a [`ProgressWriter`] be used as the output of a `tracing_subscriber::fmt` subscriber, so
+/// events print above the tree:
+///
+/// ```no_run
+/// # use declaratif::ProgressBarTree;
+/// # let tree = ProgressBarTree::<()>::new(indicatif::MultiProgress::new(), vec![]);
+/// tracing_subscriber::fmt().with_writer(tree.writer()).init();
+/// ```
+///
+/// Each event gets its own writer, which prints the event when it's dropped.
+impl<'a> MakeWriter<'a> for ProgressWriter {
+    type Writer = ProgressWriter;
+
+    fn make_writer(&'a self) -> Self::Writer {
+        self.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::LogTail;
+
+    #[test]
+    fn test_fmt_subscriber_writes_events() {
+        let tail = LogTail::new(10);
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(tail.writer())
+            .with_ansi(false)
+            .without_time()
+            .with_target(false)
+            .finish();
+
+        tracing::subscriber::with_default(subscriber, || {
+            tracing::info!("first");
+            tracing::warn!(count = 3, "second");
+        });
+
+        assert_eq!(tail.last(10), vec![" INFO first", " WARN second count=3"]);
+    }
+}
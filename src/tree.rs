@@ -1,32 +1,178 @@
 use crate::{
-    helpers::tree::group, multiprogress_bindable::MultiProgressWrapper,
+    DisplayState, LeafUpdate, ProgressSink,
+    dynamic::DynamicGroup,
+    group::{CollapsePolicy, Columns, GroupOptions, SortOrder, VisiblePriority},
+    helpers::tree::group,
+    multiprogress_bindable::{BarPath, MultiProgressWrapper},
     progress_bar_bindable::ProgressBarBindable,
+    render::render_leaf,
+    render::{RenderContext, Resolved, ResolvedGroup},
+    summary::GroupSummary,
+    theme::{TerminalCapabilities, Theme, ThemeError, Widget},
+    viewport::{self, HeightLimit},
+    writer::ProgressWriter,
 };
 use indicatif::MultiProgress;
+use std::{
+    io,
+    ops::Range,
+    sync::{Arc, Mutex},
+};
 
 pub struct ProgressBarTree<V: Send + Sync> {
     root: ProgressBarTreeContainer<V>,
+    multiprogress: MultiProgressWrapper,
+    sinks: Mutex<Vec<Box<dyn ProgressSink>>>,
+    theme: Theme,
+    capabilities: TerminalCapabilities,
+    // The theme adjusted for the terminal, as given to the bars
+    applied_theme: Arc<Theme>,
+    guides: bool,
+    height_limit: HeightLimit,
+    height_priority: VisiblePriority,
+    // Counts the bars left out when the tree doesn't fit in `height_limit`
+    hidden: ProgressBarBindable<GroupSummary>,
 }
 
+// Sorts after every other bar, including the root group's summary
+const HIDDEN_PATH: [usize; 2] = [usize::MAX, 0];
+
 impl<V: Send + Sync> ProgressBarTree<V> {
-    pub fn new(
-        multiprogress: MultiProgress,
-        mut children: Vec<ProgressBarTreeContainer<V>>,
-    ) -> Self {
+    /// Creates the tree, with the default [`Theme`] adjusted to what
+    /// [`TerminalCapabilities::detect`] finds the terminal can display.
+    pub fn new(multiprogress: MultiProgress, children: Vec<ProgressBarTreeContainer<V>>) -> Self {
         let wrapper: MultiProgressWrapper = multiprogress.into();
 
-        let mut index = 0;
-        children.iter_mut().for_each(|child| {
-            child.reparent(&mut index, wrapper.clone());
-        });
+        let mut root = group(children);
+        root.reparent(&mut Vec::new(), wrapper.clone());
+        let mut hidden =
+            ProgressBarBindable::themed(Widget::Overflow).bind_message(|summary: &GroupSummary| {
+                DisplayState::Active(summary.overflow_message())
+            });
+        hidden.reparent(wrapper.clone(), HIDDEN_PATH.to_vec());
+
+        let mut tree = Self {
+            root,
+            multiprogress: wrapper,
+            sinks: Mutex::new(Vec::new()),
+            theme: Theme::default(),
+            capabilities: TerminalCapabilities::detect(),
+            applied_theme: Arc::new(Theme::default()),
+            guides: false,
+            height_limit: HeightLimit::default(),
+            height_priority: VisiblePriority::default(),
+            hidden,
+        };
+        tree.apply_theme();
+        tree
+    }
+
+    /// Adds a sink that is passed the state of every leaf on each tick. To use sinks without any
+    /// terminal output, construct the tree with a hidden `MultiProgress`.
+    pub fn with_sink(self, sink: impl ProgressSink + 'static) -> Self {
+        self.sinks.lock().unwrap().push(Box::new(sink));
+        self
+    }
+
+    /// Styles every themed bar in the tree, including those created later by dynamic groups, with
+    /// `theme`, adjusted for the terminal's capabilities. Bars given their own style keep it.
+    /// Fails if the theme can't be drawn, see [`Theme::validate`].
+    pub fn with_theme(mut self, theme: Theme) -> Result<Self, ThemeError> {
+        theme.validate()?;
+        self.theme = theme;
+        self.apply_theme();
+        Ok(self)
+    }
+
+    /// Overrides the detected terminal capabilities, e.g. from a `--color`/`--ascii` flag.
+    pub fn with_capabilities(mut self, capabilities: TerminalCapabilities) -> Self {
+        self.capabilities = capabilities;
+        self.apply_theme();
+        self
+    }
+
+    fn apply_theme(&mut self) {
+        self.applied_theme = Arc::new(self.theme.clone().for_terminal(self.capabilities));
+        self.root.set_theme(&self.applied_theme);
+        self.hidden.set_theme(&self.applied_theme);
+    }
 
-        Self {
-            root: group(children),
+    /// Limits the children at the top of the tree, see
+    /// [`ProgressBarTreeContainer::with_max_visible`].
+    pub fn with_max_visible(mut self, max_visible: usize, priority: VisiblePriority) -> Self {
+        if let Some(options) = self.root.group_options_mut() {
+            options.set_max_visible(max_visible);
+            options.priority = priority;
         }
+        // Places the new overflow bar
+        self.root
+            .reparent(&mut Vec::new(), self.multiprogress.clone());
+        self.apply_theme();
+        self
+    }
+
+    /// Limits how many lines the tree draws, see [`HeightLimit`]. `priority` picks which bars are
+    /// drawn when they don't all fit. By default every line is drawn, with active bars first once
+    /// a limit is set.
+    pub fn with_height_limit(mut self, limit: HeightLimit, priority: VisiblePriority) -> Self {
+        self.height_limit = limit;
+        self.height_priority = priority;
+        self
+    }
+
+    /// Draws tree guides (`├─`, `└─`, `│`) before the children of nested groups, instead of just
+    /// indenting them. The guides are worked out from the bars visible on each tick, so they stay
+    /// connected as bars appear and disappear. Themed bars show them automatically; templates
+    /// given to other bars can include them with the `{indent}` key.
+    pub fn with_guides(mut self, guides: bool) -> Self {
+        self.guides = guides;
+        self
+    }
+
+    /// Prints a line above the tree. Like `MultiProgress::println`, this does nothing if the draw
+    /// target is hidden, so a tree that only feeds its sinks stays silent.
+    pub fn println(&self, msg: impl AsRef<str>) -> io::Result<()> {
+        self.multiprogress.println(msg)
+    }
+
+    /// Hides the tree while `f` runs, e.g. to write directly to the terminal, then redraws it.
+    pub fn suspend<F: FnOnce() -> R, R>(&self, f: F) -> R {
+        self.multiprogress.suspend(f)
+    }
+
+    /// A [`std::io::Write`] adapter that prints each line above the tree. The writer holds its own
+    /// handle to the terminal, so it can be moved to other threads or outlive the tree.
+    pub fn writer(&self) -> ProgressWriter {
+        ProgressWriter::new(self.multiprogress.clone())
     }
 
     pub fn tick(&self, model: &V) {
-        self.root.tick(model);
+        let mut sinks = self.sinks.lock().unwrap();
+        let mut path = Vec::new();
+        let mut resolved = self.root.resolve(model, true, &mut path, &mut |leaf| {
+            for sink in sinks.iter_mut() {
+                sink.leaf_ticked(leaf);
+            }
+        });
+
+        // Bars that don't fit are hidden after the sinks have seen them
+        let hidden = match (&mut resolved, self.height_limit.lines()) {
+            (Resolved::Group(root), Some(height)) => {
+                viewport::fit(root, height, self.height_priority)
+            }
+            _ => None,
+        };
+        let mut context = RenderContext::new(self.applied_theme.clone(), self.guides);
+        self.root.render(&resolved, &mut context);
+        let hidden = match hidden {
+            Some(summary) => self.hidden.resolve(&summary, true),
+            None => DisplayState::FinishedAndHidden,
+        };
+        render_leaf(&self.hidden, &hidden, &mut context);
+
+        for sink in sinks.iter_mut() {
+            sink.tick_finished();
+        }
     }
 }
 
@@ -37,6 +183,13 @@ pub enum ProgressBarTreeContainer<V: Send + Sync> {
     Node(
         Vec<ProgressBarTreeContainer<V>>,
         Option<DisplayCondition<V>>,
+        GroupOptions<V>,
+    ),
+    /// Children created and removed as the model changes, see [`crate::helpers::tree::for_each`]
+    Dynamic(
+        DynamicGroup<V>,
+        Option<DisplayCondition<V>>,
+        GroupOptions<V>,
     ),
 }
 
@@ -59,31 +212,203 @@ impl<V: Send + Sync> From<Vec<ProgressBarTreeContainer<V>>> for ProgressBarTreeC
 }
 
 impl<V: Send + Sync> ProgressBarTreeContainer<V> {
-    pub fn with_display_condition(self, condition: DisplayCondition<V>) -> Self {
+    pub fn with_display_condition(mut self, condition: DisplayCondition<V>) -> Self {
+        match &mut self {
+            ProgressBarTreeContainer::Leaf(_, current)
+            | ProgressBarTreeContainer::Node(_, current, _)
+            | ProgressBarTreeContainer::Dynamic(_, current, _) => *current = Some(condition),
+        }
+        self
+    }
+
+    /// Draws the children of this group one level deeper than the group, as children of the item
+    /// above it (usually a header bar), see [`ProgressBarTree::with_guides`]. Has no effect on a
+    /// single bar.
+    pub fn nested(mut self) -> Self {
+        if let Some(options) = self.group_options_mut() {
+            options.nested = true;
+        }
+        self
+    }
+
+    /// Draws `header` above the group's children, bound to a [`GroupSummary`] of them. A single
+    /// bar is first wrapped in a group.
+    pub fn with_header(self, header: ProgressBarBindable<GroupSummary>) -> Self {
+        let mut group = match self {
+            ProgressBarTreeContainer::Leaf(..) => group(vec![self]),
+            group => group,
+        };
+        if let Some(options) = group.group_options_mut() {
+            options.header = Some(Box::new(header));
+        }
+        group
+    }
+
+    /// A bar drawn in place of the children while the group is collapsed, bound to a
+    /// [`GroupSummary`] of them, e.g. [`crate::helpers::widgets::summary_done`]. A single bar is
+    /// first wrapped in a group.
+    pub fn with_summary(self, summary: ProgressBarBindable<GroupSummary>) -> Self {
+        let mut group = match self {
+            ProgressBarTreeContainer::Leaf(..) => group(vec![self]),
+            group => group,
+        };
+        if let Some(options) = group.group_options_mut() {
+            options.summary = Some(Box::new(summary));
+        }
+        group
+    }
+
+    /// Sets when the group hides its children, leaving only its header and summary. Has no effect
+    /// on a single bar.
+    pub fn with_collapse(mut self, policy: CollapsePolicy<V>) -> Self {
+        if let Some(options) = self.group_options_mut() {
+            options.collapse = policy;
+        }
+        self
+    }
+
+    /// Draws the group's children in `order`, re-sorting them on every tick. Bars that move are
+    /// moved in place rather than recreated. Has no effect on a single bar.
+    pub fn with_sort(mut self, order: SortOrder<V>) -> Self {
+        if let Some(options) = self.group_options_mut() {
+            options.sort = order;
+        }
+        self
+    }
+
+    /// Pads the given parts of the bars directly in the group to the same width on each tick, so
+    /// they line up in columns. Has no effect on a single bar.
+    pub fn with_columns(mut self, columns: Columns) -> Self {
+        if let Some(options) = self.group_options_mut() {
+            options.columns = columns;
+        }
+        self
+    }
+
+    /// Shows at most `max_visible` of the group's visible children, chosen by the group's
+    /// [`VisiblePriority`], followed by a line counting the rest, e.g.
+    /// `"… and 42 more (12 queued, 30 done)"`. A child followed by a nested group counts as one.
+    /// Has no effect on a single bar.
+    pub fn with_max_visible(mut self, max_visible: usize) -> Self {
+        if let Some(options) = self.group_options_mut() {
+            options.set_max_visible(max_visible);
+        }
+        self
+    }
+
+    /// Sets which children are shown when there are more than `max_visible`.
+    pub fn with_visible_priority(mut self, priority: VisiblePriority) -> Self {
+        if let Some(options) = self.group_options_mut() {
+            options.priority = priority;
+        }
+        self
+    }
+
+    fn group_options_mut(&mut self) -> Option<&mut GroupOptions<V>> {
         match self {
-            ProgressBarTreeContainer::Leaf(bar, _) => {
-                ProgressBarTreeContainer::Leaf(bar, Some(condition))
-            }
-            ProgressBarTreeContainer::Node(children, _) => {
-                ProgressBarTreeContainer::Node(children, Some(condition))
-            }
+            ProgressBarTreeContainer::Leaf(..) => None,
+            ProgressBarTreeContainer::Node(_, _, options)
+            | ProgressBarTreeContainer::Dynamic(_, _, options) => Some(options),
         }
     }
 
     pub fn tick(&self, model: &V) {
-        self.tick_inner(model, true);
+        self.tick_inner(model, true, &mut Vec::new(), &mut |_| {});
     }
 
-    fn tick_inner(&self, model: &V, parent_can_display: bool) {
+    /// Resolves and renders in one go, for when the layout doesn't matter, e.g. hiding children
+    /// that are about to be removed.
+    pub(crate) fn tick_inner(
+        &self,
+        model: &V,
+        parent_can_display: bool,
+        path: &mut Vec<usize>,
+        observer: &mut dyn FnMut(&LeafUpdate<'_>),
+    ) {
+        let resolved = self.resolve(model, parent_can_display, path, observer);
+        self.render(&resolved, &mut RenderContext::default());
+    }
+
+    /// Works out the state of every bar in the container without drawing anything, passing each
+    /// leaf's state to `observer`.
+    pub(crate) fn resolve(
+        &self,
+        model: &V,
+        parent_can_display: bool,
+        path: &mut Vec<usize>,
+        observer: &mut dyn FnMut(&LeafUpdate<'_>),
+    ) -> Resolved {
         let parent_can_display = parent_can_display && self.can_display(model);
         match self {
             ProgressBarTreeContainer::Leaf(bar, _) => {
-                bar.tick_with_display_override(model, parent_can_display);
+                let state = bar.resolve(model, parent_can_display);
+                observer(&LeafUpdate {
+                    id: bar.id(),
+                    path,
+                    state: &state,
+                });
+                Resolved::Leaf(state, bar.info())
             }
-            ProgressBarTreeContainer::Node(children, _) => {
-                for child in children {
-                    child.tick_inner(model, parent_can_display);
-                }
+            ProgressBarTreeContainer::Node(children, _, options) => {
+                let children = children
+                    .iter()
+                    .enumerate()
+                    .map(|(child_index, child)| {
+                        path.push(child_index);
+                        let resolved = child.resolve(model, parent_can_display, path, observer);
+                        path.pop();
+                        (child_index, resolved)
+                    })
+                    .collect();
+                Resolved::Group(Box::new(options.resolve(
+                    model,
+                    children,
+                    parent_can_display,
+                    path,
+                    observer,
+                )))
+            }
+            ProgressBarTreeContainer::Dynamic(children, _, options) => {
+                let children = children.resolve(model, parent_can_display, path, observer);
+                Resolved::Group(Box::new(options.resolve(
+                    model,
+                    children,
+                    parent_can_display,
+                    path,
+                    observer,
+                )))
+            }
+        }
+    }
+
+    /// Draws the states from [`Self::resolve`].
+    pub(crate) fn render(&self, resolved: &Resolved, context: &mut RenderContext) {
+        match (self, resolved) {
+            (ProgressBarTreeContainer::Leaf(bar, _), Resolved::Leaf(state, _)) => {
+                render_leaf(bar, state, context);
+            }
+            (ProgressBarTreeContainer::Node(children, _, options), Resolved::Group(group)) => {
+                let children: Vec<_> = children.iter().collect();
+                render_group(&children, group, options, context);
+            }
+            (ProgressBarTreeContainer::Dynamic(children, _, options), Resolved::Group(group)) => {
+                children.visit(&mut |children| render_group(children, group, options, context));
+            }
+            _ => unreachable!("Resolved state doesn't match the container"),
+        }
+    }
+
+    /// Removes every bar in the container, without resolving them.
+    fn hide(&self) {
+        match self {
+            ProgressBarTreeContainer::Leaf(bar, _) => bar.render(&DisplayState::FinishedAndHidden),
+            ProgressBarTreeContainer::Node(children, _, options) => {
+                options.hide();
+                children.iter().for_each(Self::hide);
+            }
+            ProgressBarTreeContainer::Dynamic(children, _, options) => {
+                options.hide();
+                children.visit(&mut |children| children.iter().for_each(|child| child.hide()));
             }
         }
     }
@@ -91,23 +416,130 @@ impl<V: Send + Sync> ProgressBarTreeContainer<V> {
     fn can_display(&self, model: &V) -> bool {
         match self {
             ProgressBarTreeContainer::Leaf(_, condition)
-            | ProgressBarTreeContainer::Node(_, condition) => {
+            | ProgressBarTreeContainer::Node(_, condition, _)
+            | ProgressBarTreeContainer::Dynamic(_, condition, _) => {
                 condition.as_ref().map(|c| c(model)).unwrap_or(true)
             }
         }
     }
 
-    pub(crate) fn reparent(&mut self, index: &mut usize, multiprogress: MultiProgressWrapper) {
+    pub(crate) fn reparent(&mut self, path: &mut BarPath, multiprogress: MultiProgressWrapper) {
         match self {
             ProgressBarTreeContainer::Leaf(bar, _) => {
-                bar.reparent(multiprogress, *index);
-                *index += 1;
+                bar.reparent(multiprogress, path.clone());
+            }
+            ProgressBarTreeContainer::Node(children, _, options) => {
+                options.reparent(path, &multiprogress);
+                for (child_index, child) in children.iter_mut().enumerate() {
+                    path.push(child_index);
+                    child.reparent(path, multiprogress.clone());
+                    path.pop();
+                }
             }
-            ProgressBarTreeContainer::Node(children, _) => {
-                for child in children {
-                    child.reparent(index, multiprogress.clone());
+            ProgressBarTreeContainer::Dynamic(children, _, options) => {
+                options.reparent(path, &multiprogress);
+                children.reparent(path, multiprogress);
+            }
+        }
+    }
+
+    pub(crate) fn set_theme(&mut self, theme: &Arc<Theme>) {
+        match self {
+            ProgressBarTreeContainer::Leaf(bar, _) => bar.set_theme(theme),
+            ProgressBarTreeContainer::Node(children, _, options) => {
+                options.set_theme(theme);
+                for child in children.iter_mut() {
+                    child.set_theme(theme);
                 }
             }
+            ProgressBarTreeContainer::Dynamic(children, _, options) => {
+                options.set_theme(theme);
+                children.set_theme(theme);
+            }
         }
     }
 }
+
+fn render_group<V: Send + Sync>(
+    children: &[&ProgressBarTreeContainer<V>],
+    group: &ResolvedGroup,
+    options: &GroupOptions<V>,
+    context: &mut RenderContext,
+) {
+    options.place_children(group);
+    options.render_header(&group.header, context);
+
+    let items = group.ordered_items();
+    let visible = |index: usize, item: &Range<usize>| {
+        !group.collapsed
+            && group.is_shown(index)
+            && group.children[item.clone()]
+                .iter()
+                .any(Resolved::is_visible)
+    };
+    // The overflow line comes after the children, so takes the last guide when it's shown
+    let overflow_shown = group.overflow.as_ref().is_some_and(|state| {
+        !matches!(
+            state,
+            DisplayState::NotStarted | DisplayState::FinishedAndHidden
+        )
+    });
+    let last_visible = items
+        .iter()
+        .rposition(|(index, item)| visible(*index, item))
+        .filter(|_| !overflow_shown);
+
+    if options.columns != Columns::default() {
+        align_columns(children, group, &items, options.columns);
+    }
+
+    for (position, (index, item)) in items.iter().enumerate() {
+        let children = children[item.clone()]
+            .iter()
+            .zip(&group.children[item.clone()]);
+        if group.collapsed || !group.is_shown(*index) {
+            children.for_each(|(child, _)| child.hide());
+        } else if options.nested {
+            context.nested(Some(position) == last_visible, |context| {
+                children.for_each(|(child, state)| child.render(state, context));
+            });
+        } else {
+            children.for_each(|(child, state)| child.render(state, context));
+        }
+    }
+
+    options.render_overflow(&group.overflow, context);
+    options.render_summary(&group.summary, context);
+}
+
+/// Pads the visible leaves directly in the group to common widths.
+fn align_columns<V: Send + Sync>(
+    children: &[&ProgressBarTreeContainer<V>],
+    group: &ResolvedGroup,
+    items: &[(usize, Range<usize>)],
+    columns: Columns,
+) {
+    let leaves = || {
+        items
+            .iter()
+            .filter(|(index, _)| !group.collapsed && group.is_shown(*index))
+            .flat_map(|(_, item)| {
+                children[item.clone()]
+                    .iter()
+                    .zip(&group.children[item.clone()])
+            })
+            .filter_map(|(child, state)| match (child, state) {
+                (ProgressBarTreeContainer::Leaf(bar, _), Resolved::Leaf(state, _)) => {
+                    Some((bar, state))
+                }
+                _ => None,
+            })
+    };
+    let widths = columns.measure(leaves().filter_map(|(_, state)| match state {
+        DisplayState::Active(progress)
+        | DisplayState::Finished(progress)
+        | DisplayState::Failed(progress) => Some(progress),
+        DisplayState::NotStarted | DisplayState::FinishedAndHidden => None,
+    }));
+    leaves().for_each(|(bar, _)| bar.set_column_widths(widths));
+}
@@ -0,0 +1,122 @@
+use crate::{DisplayState, ProgressBarTreeContainer, tasks::TaskState};
+
+/// A view model that knows its own default layout, usually implemented with
+/// `#[derive(ProgressView)]` (requires the `derive` feature).
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// use declaratif::{DisplayState, ProgressBarTree, ProgressView};
+///
+/// struct Transfer {
+///     done: u64,
+///     total: u64,
+/// }
+///
+/// #[derive(ProgressView)]
+/// struct Download {
+///     #[state]
+///     state: DisplayState<()>,
+///     #[message]
+///     file: String,
+///     #[progress(pos = "done", len = "total")]
+///     transfer: Transfer,
+/// }
+///
+/// let tree = ProgressBarTree::new(indicatif::MultiProgress::new(), Download::progress_tree());
+/// # }
+/// ```
+pub trait ProgressView: Sized + Send + Sync {
+    fn progress_tree() -> Vec<ProgressBarTreeContainer<Self>>;
+}
+
+/// A field that drives the lifecycle of a derived [`ProgressView`], marked with `#[state]`.
+pub trait ViewState {
+    fn display_state(&self) -> DisplayState<()>;
+}
+
+impl ViewState for DisplayState<()> {
+    fn display_state(&self) -> DisplayState<()> {
+        self.clone()
+    }
+}
+
+impl ViewState for TaskState {
+    fn display_state(&self) -> DisplayState<()> {
+        match self {
+            TaskState::Pending => DisplayState::NotStarted,
+            TaskState::Active => DisplayState::Active(()),
+            TaskState::Finished => DisplayState::Finished(()),
+            TaskState::Failed => DisplayState::Failed(()),
+            TaskState::Hidden => DisplayState::FinishedAndHidden,
+        }
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use super::*;
+    use crate::{ProgressBarState, ProgressBarTree, ProgressView};
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    struct Counter {
+        done: u32,
+        total: u32,
+    }
+
+    #[derive(ProgressView)]
+    struct Job {
+        #[state]
+        state: TaskState,
+        #[message]
+        name: String,
+        #[progress(pos = "done", len = "total")]
+        items: Counter,
+    }
+
+    #[test]
+    fn test_derived_bindings_follow_state() {
+        let mut job = Job {
+            state: TaskState::Pending,
+            name: "build".to_string(),
+            items: Counter { done: 3, total: 7 },
+        };
+        assert_eq!(job.name_message(), DisplayState::NotStarted);
+
+        job.state = TaskState::Active;
+        assert_eq!(
+            job.name_message(),
+            DisplayState::Active("build".to_string())
+        );
+        assert_eq!(
+            job.items_progress(),
+            DisplayState::Active(ProgressBarState::new(None, None, 3, 7))
+        );
+
+        job.state = TaskState::Failed;
+        assert_eq!(
+            job.name_message(),
+            DisplayState::Failed("build".to_string())
+        );
+    }
+
+    #[test]
+    fn test_derived_layout() {
+        let term = InMemoryTerm::new(10, 40);
+        let tree = ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            Job::progress_tree(),
+        );
+        let job = Job {
+            state: TaskState::Active,
+            name: "build".to_string(),
+            items: Counter { done: 3, total: 7 },
+        };
+
+        tree.tick(&job);
+        let contents = term.contents();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], "build");
+        assert!(lines[1].ends_with("3/7"));
+    }
+}
@@ -0,0 +1,231 @@
+use crate::{
+    DisplayState, ProgressBarState,
+    group::{Rank, VisiblePriority},
+    progress_bar_bindable::BarInfo,
+    render::{Resolved, ResolvedGroup, is_drawn},
+    summary::GroupSummary,
+};
+/// How many lines a [`crate::ProgressBarTree`] draws. When the visible bars don't fit, the tree
+/// draws those ranked highest by its [`VisiblePriority`], followed by a line counting the rest,
+/// e.g. `"… and 42 more (12 queued, 30 done)"`. The bars left out are still resolved and passed to
+/// sinks, they just aren't drawn.
+#[derive(Debug, Clone, Default)]
+pub enum HeightLimit {
+    /// The height of the terminal the tree draws to, read on each tick, e.g. `Term::stderr()`
+    /// for a `MultiProgress` drawing to its default target. No limit when it isn't a terminal.
+    Terminal(console::Term),
+    /// A fixed number of lines, e.g. for a draw target that isn't a terminal
+    Lines(usize),
+    #[default]
+    Unlimited,
+}
+
+impl HeightLimit {
+    pub(crate) fn lines(&self) -> Option<usize> {
+        match self {
+            HeightLimit::Terminal(term) => term.size_checked().map(|(rows, _)| rows.into()),
+            HeightLimit::Lines(lines) => Some(*lines),
+            HeightLimit::Unlimited => None,
+        }
+    }
+}
+
+// A line the tree would draw
+#[derive(Clone, Copy)]
+struct Line {
+    index: usize,
+    // The header line drawn above this one, which has to be kept along with it
+    parent: Option<usize>,
+    // Whether the line is a bar rather than a group's header, summary or overflow line
+    leaf: bool,
+    info: BarInfo,
+}
+
+/// Hides the lines of `root` that don't fit in `height`, keeping those ranked highest by
+/// `priority` along with the headers above them. Returns a summary of the hidden bars if any were
+/// hidden, leaving a line free to show it.
+pub(crate) fn fit(
+    root: &mut ResolvedGroup,
+    height: usize,
+    priority: VisiblePriority,
+) -> Option<GroupSummary> {
+    let mut lines = Vec::new();
+    visit_lines(root, None, &mut 0, &mut |line, state| {
+        lines.push((line, Rank::new(state, line.info.finished_at)));
+    });
+    if lines.len() <= height {
+        return None;
+    }
+
+    let mut order: Vec<(usize, Rank)> = lines
+        .iter()
+        .map(|(line, rank)| (line.index, *rank))
+        .collect();
+    priority.sort(&mut order);
+
+    // Leaves a line for the summary of the rest
+    let budget = height.saturating_sub(1);
+    let mut kept = vec![false; lines.len()];
+    let mut used = 0;
+    for (index, _) in order {
+        let mut needed = Vec::new();
+        let mut next = Some(index);
+        while let Some(index) = next.filter(|index| !kept[*index]) {
+            needed.push(index);
+            next = lines[index].0.parent;
+        }
+        if used + needed.len() <= budget {
+            used += needed.len();
+            needed.into_iter().for_each(|index| kept[index] = true);
+        }
+    }
+
+    let mut hidden = Vec::new();
+    visit_lines(root, None, &mut 0, &mut |line, state| {
+        if !kept[line.index] {
+            if line.leaf {
+                hidden.push(Resolved::Leaf(state.clone(), line.info));
+            }
+            *state = DisplayState::FinishedAndHidden;
+        }
+    });
+    Some(GroupSummary::from_children(&hidden))
+}
+
+/// Calls `f` with each visible line of `group`, in the order they're drawn.
+fn visit_lines(
+    group: &mut ResolvedGroup,
+    parent: Option<usize>,
+    count: &mut usize,
+    f: &mut dyn FnMut(Line, &mut DisplayState<ProgressBarState>),
+) {
+    let parent = match &mut group.header {
+        Some(header) => visit_line(header, parent, false, BarInfo::default(), count, f).or(parent),
+        None => parent,
+    };
+    if !group.collapsed {
+        for (index, item) in group.ordered_items() {
+            if !group.is_shown(index) {
+                continue;
+            }
+            for child in &mut group.children[item] {
+                match child {
+                    Resolved::Leaf(state, info) => {
+                        visit_line(state, parent, true, *info, count, f);
+                    }
+                    Resolved::Group(group) => visit_lines(group, parent, count, f),
+                }
+            }
+        }
+    }
+    for state in [&mut group.overflow, &mut group.summary]
+        .into_iter()
+        .flatten()
+    {
+        visit_line(state, parent, false, BarInfo::default(), count, f);
+    }
+}
+
+// Passes the line to `f` if it's visible, returning its index
+fn visit_line(
+    state: &mut DisplayState<ProgressBarState>,
+    parent: Option<usize>,
+    leaf: bool,
+    info: BarInfo,
+    count: &mut usize,
+    f: &mut dyn FnMut(Line, &mut DisplayState<ProgressBarState>),
+) -> Option<usize> {
+    if !is_drawn(state, &info) {
+        return None;
+    }
+    let index = *count;
+    *count += 1;
+    f(
+        Line {
+            index,
+            parent,
+            leaf,
+            info,
+        },
+        state,
+    );
+    Some(index)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        DisplayState, GroupSummary, HeightLimit, ProgressBarTree, TerminalCapabilities,
+        VisiblePriority,
+        helpers::{tree::for_each, widgets::*},
+    };
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    type Model = Vec<DisplayState<String>>;
+
+    fn jobs(term: &InMemoryTerm, header: bool) -> ProgressBarTree<Model> {
+        let jobs = for_each(
+            |model: &Model| (0..model.len()).collect(),
+            |index: &usize| {
+                let index = *index;
+                message(move |model: &Model| model[index].clone()).into()
+            },
+        )
+        .nested();
+        let children = if header {
+            vec![jobs.with_header(message(|summary: &GroupSummary| {
+                summary.counts_message("jobs")
+            }))]
+        } else {
+            vec![message_static("jobs").into(), jobs]
+        };
+        ProgressBarTree::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            children,
+        )
+        .with_capabilities(TerminalCapabilities::ascii_only())
+    }
+
+    #[test]
+    fn test_height_limit() {
+        let finished = |name: &str| DisplayState::Finished(name.to_string());
+        let active = |name: &str| DisplayState::Active(name.to_string());
+        let failed = |name: &str| DisplayState::Failed(name.to_string());
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree =
+            jobs(&term, false).with_height_limit(HeightLimit::Lines(4), VisiblePriority::default());
+        tree.tick(&vec![finished("a"), active("b")]);
+        assert_eq!(term.contents(), "jobs\n  a\n  b");
+
+        // `c` finished after `a` and the static line, so is kept over them
+        tree.tick(&vec![
+            finished("a"),
+            active("b"),
+            finished("c"),
+            active("d"),
+        ]);
+        tree.tick(&vec![
+            finished("a"),
+            active("b"),
+            finished("c"),
+            failed("d"),
+        ]);
+        // The static line is left out too, but isn't counted
+        assert_eq!(term.contents(), "  b\n  c\n  x d\n... and 1 more (1 done)");
+    }
+
+    #[test]
+    fn test_height_limit_keeps_headers() {
+        let active = |name: &str| DisplayState::Active(name.to_string());
+
+        let term = InMemoryTerm::new(10, 40);
+        let tree =
+            jobs(&term, true).with_height_limit(HeightLimit::Lines(3), VisiblePriority::Last);
+        tree.tick(&vec![active("a"), active("b"), active("c")]);
+        assert_eq!(
+            term.contents(),
+            "jobs 0/3 done\n  c\n... and 2 more (2 active)"
+        );
+    }
+}
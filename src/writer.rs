@@ -0,0 +1,215 @@
+use crate::multiprogress_bindable::MultiProgressWrapper;
+use std::{
+    collections::VecDeque,
+    io::{self, Write},
+    sync::{Arc, Mutex},
+};
+
+/// A [`Write`] adapter that prints complete lines above the bars of a
+/// [`crate::ProgressBarTree`], so other output doesn't tear through the rendering. Partial lines
+/// are buffered until a newline is written or the writer is flushed.
+///
+/// If the tree's draw target is hidden lines aren't printed, so a tree that only feeds its sinks
+/// stays silent. Lines can also be kept in a [`LogTail`] to show them inside the tree, or to keep
+/// them while hidden.
+pub struct ProgressWriter {
+    multiprogress: Option<MultiProgressWrapper>,
+    tail: Option<LogTail>,
+    buffer: Vec<u8>,
+}
+
+impl ProgressWriter {
+    pub(crate) fn new(multiprogress: MultiProgressWrapper) -> Self {
+        Self {
+            multiprogress: Some(multiprogress),
+            tail: None,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Also pushes every line to `tail`.
+    pub fn with_tail(mut self, tail: LogTail) -> Self {
+        self.tail = Some(tail);
+        self
+    }
+
+    fn print_line(&self, line: &[u8]) -> io::Result<()> {
+        let line = String::from_utf8_lossy(line);
+        let line = line.strip_suffix('\r').unwrap_or(&line);
+        if let Some(tail) = &self.tail {
+            tail.push(line);
+        }
+        match &self.multiprogress {
+            Some(multiprogress) => multiprogress.println(line),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Clone for ProgressWriter {
+    /// The clone prints to the same tree, but doesn't share any buffered partial line.
+    fn clone(&self) -> Self {
+        Self {
+            multiprogress: self.multiprogress.clone(),
+            tail: self.tail.clone(),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+impl Write for ProgressWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buffer.extend_from_slice(buf);
+        while let Some(newline) = self.buffer.iter().position(|b| *b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=newline).collect();
+            self.print_line(&line[..newline])?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.buffer.is_empty() {
+            let line = std::mem::take(&mut self.buffer);
+            self.print_line(&line)?;
+        }
+        Ok(())
+    }
+}
+
+impl Drop for ProgressWriter {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// The most recent lines of some output, shared between whatever writes them and a
+/// [`crate::helpers::tree::log_tail`] panel that shows them inside a tree.
+#[derive(Clone)]
+pub struct LogTail {
+    lines: Arc<Mutex<VecDeque<String>>>,
+    capacity: usize,
+}
+
+impl LogTail {
+    /// A tail keeping the last `capacity` lines. With a capacity of zero nothing is kept.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+            capacity,
+        }
+    }
+
+    pub fn push(&self, line: impl Into<String>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut lines = self.lines.lock().unwrap();
+        while lines.len() >= self.capacity {
+            lines.pop_front();
+        }
+        lines.push_back(line.into());
+    }
+
+    /// The last `count` lines, oldest first.
+    pub fn last(&self, count: usize) -> Vec<String> {
+        let lines = self.lines.lock().unwrap();
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+
+    /// A writer that only pushes lines to this tail, without printing them.
+    pub fn writer(&self) -> ProgressWriter {
+        ProgressWriter {
+            multiprogress: None,
+            tail: Some(self.clone()),
+            buffer: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProgressBarTree, helpers::tree::log_tail, helpers::widgets::*};
+    use indicatif::{InMemoryTerm, MultiProgress, ProgressDrawTarget};
+
+    #[test]
+    fn test_writer_prints_whole_lines_above_bars() {
+        let term = InMemoryTerm::new(10, 80);
+        let tree = ProgressBarTree::<()>::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![message_static("Working").into()],
+        );
+        tree.tick(&());
+
+        let mut writer = tree.writer();
+        write!(writer, "first line\nsecond").unwrap();
+        assert_eq!(term.contents(), "first line\nWorking");
+
+        writeln!(writer, " line").unwrap();
+        write!(writer, "unterminated").unwrap();
+        drop(writer);
+        tree.println("from println").unwrap();
+        assert_eq!(
+            term.contents(),
+            "first line\nsecond line\nunterminated\nfrom println\nWorking"
+        );
+
+        let suspended = tree.suspend(|| term.contents());
+        assert_eq!(
+            suspended,
+            "first line\nsecond line\nunterminated\nfrom println"
+        );
+    }
+
+    #[test]
+    fn test_hidden_tree_only_keeps_lines_in_its_tail() {
+        let tail = LogTail::new(10);
+        let tree = ProgressBarTree::<()>::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::hidden()),
+            vec![],
+        );
+        let mut writer = tree.writer().with_tail(tail.clone());
+        writeln!(writer, "kept").unwrap();
+        tree.println("dropped").unwrap();
+        assert_eq!(tail.last(2), vec!["kept"]);
+    }
+
+    #[test]
+    fn test_log_tail_keeps_last_lines() {
+        let tail = LogTail::new(3);
+        let mut writer = tail.writer();
+        write!(writer, "one\ntwo\nthree\nfour\nfi").unwrap();
+        assert_eq!(tail.last(5), vec!["two", "three", "four"]);
+        assert_eq!(tail.last(2), vec!["three", "four"]);
+
+        writer.flush().unwrap();
+        assert_eq!(tail.last(2), vec!["four", "fi"]);
+
+        let empty = LogTail::new(0);
+        empty.push("dropped");
+        assert!(empty.last(1).is_empty());
+    }
+
+    #[test]
+    fn test_log_tail_panel() {
+        let term = InMemoryTerm::new(10, 80);
+        let tail = LogTail::new(10);
+        let tree = ProgressBarTree::<()>::new(
+            MultiProgress::with_draw_target(ProgressDrawTarget::term_like(Box::new(term.clone()))),
+            vec![message_static("Header").into(), log_tail(&tail, 2)],
+        );
+
+        tail.push("one");
+        tree.tick(&());
+        assert_eq!(term.contents(), "Header\none");
+
+        tail.push("two");
+        tail.push("three");
+        tree.tick(&());
+        assert_eq!(term.contents(), "Header\ntwo\nthree");
+    }
+}